@@ -1,57 +1,440 @@
-use crate::config::DotfileEntry;
+use crate::backup::{self, Snapshot};
+use crate::config::{DotfileEntry, ManifestProblem};
+use crate::deps::{check_dependencies, locate_dependencies, DepCheck, DepStatus};
+use crate::diff::{diff_entry, DiffLine};
+use crate::graphics::{Protocol, Screenshot};
+use crate::highlight::{load_file, FileView};
+use crate::installer::{
+    install_order, plan_install, spawn_batch_install, spawn_install, EntryOutcome, InstallEvent,
+    InstallHandle, InstallPlan,
+};
+use crate::palette::{entry_palette, Swatch};
+use crate::profile::{
+    find_profile, is_applied, load_profiles, plan_profile, record_applied, Profile, ProfileAction,
+    ProfileItem,
+};
 use crate::scanner::DotfileScanner;
+use crate::search::{self, Filter, FilteredEntry};
+use crate::settings::{BrowseSettings, Settings};
+use crate::state::InstallState;
+use crate::status::{entry_status, Status};
+use crate::tree::{build_rows, BrowseRow, RowKey};
+use crate::validate::collect_problems;
+use crate::watch::RootWatcher;
 use color_eyre::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum View {
     Home,
     Browse,
     Preview,
+    Diff,
     Installing,
+    Problems,
+    Profiles,
+}
+
+/// One entry of a batch install, as shown in the Installing view.
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub name: String,
+    pub path: PathBuf,
+    pub outcome: EntryOutcome,
 }
 
 pub struct App {
     pub view: View,
     pub dotfiles: Vec<DotfileEntry>,
-    pub selected_index: usize,
+    /// The browse tree: category groups, directory groups and entries.
+    pub rows: Vec<BrowseRow>,
+    /// Selected row of `rows`.
+    pub cursor: usize,
+    /// Keys of the collapsed groups.
+    collapsed: HashSet<String>,
+    pub browse: BrowseSettings,
+    /// Search query and chips narrowing the browse list.
+    pub filter: Filter,
+    /// Entries shown in the browse list, in display order.
+    pub filtered: Vec<FilteredEntry>,
+    /// Typing goes into the search query.
+    pub searching: bool,
+    /// Entries marked for a batch install, by path.
+    pub marked: HashSet<PathBuf>,
     pub should_quit: bool,
-    pub install_output: Option<String>,
+    /// Log of the current or last install, rollback or error.
+    pub install_output: Vec<String>,
     pub install_success: bool,
+    /// Install running in the background, if any.
+    pub install_job: Option<InstallHandle>,
+    pub install_started: Instant,
+    pub install_elapsed: Duration,
+    /// When set, installs only produce a plan.
+    pub dry_run: bool,
+    /// When set, batch installs carry on past a failed entry.
+    pub keep_going: bool,
+    /// Plans of the last dry run, in install order.
+    pub install_plans: Vec<InstallPlan>,
+    /// Progress of every entry when several are installed at once.
+    pub batch: Vec<BatchItem>,
+    /// Dependency checks for the entry shown in the Preview view.
+    pub dep_checks: Vec<DepCheck>,
+    /// Version checks still running for `dep_checks`, which stay
+    /// `Unchecked` until they arrive.
+    dep_check_job: Option<Receiver<Vec<DepCheck>>>,
+    /// File of the previewed entry selected in the Files panel.
+    pub preview_file: usize,
+    pub file_view: Option<FileView>,
+    pub file_scroll: u16,
+    /// Colors of the previewed entry, from its `[palette]` or its files.
+    pub palette: Vec<Swatch>,
+    /// Screenshot of the previewed entry, or why it could not be loaded.
+    pub screenshot: Option<Result<Screenshot, String>>,
+    /// How the screenshot is drawn, already resolved from `Auto`.
+    pub graphics: Protocol,
+    pub diff_lines: Vec<DiffLine>,
+    pub diff_scroll: u16,
+    pub state: InstallState,
+    /// Drift status of every entry, keyed by entry path.
+    pub statuses: HashMap<PathBuf, Status>,
+    /// Snapshot awaiting confirmation in the rollback dialog.
+    pub pending_rollback: Option<Snapshot>,
+    /// Manifests that failed to load and entries that fail validation.
+    pub problems: Vec<ManifestProblem>,
+    pub problems_scroll: u16,
+    pub profiles: Vec<Profile>,
+    /// Selected row of `profiles`.
+    pub profile_cursor: usize,
+    /// What applying the selected profile would do to each of its entries.
+    pub profile_items: Vec<ProfileItem>,
+    /// Whether the recorded profile is still fully in place.
+    active_profile_applied: bool,
+    /// Profile being applied by the running batch install.
+    applying_profile: Option<String>,
+    /// Where Esc leaves the Installing view for.
+    install_origin: View,
+    /// Where Esc leaves the Problems view for.
+    problems_origin: View,
+    /// Where Esc leaves the Profiles view for.
+    profiles_origin: View,
+    /// Short message for the footer, e.g. after a reload.
+    notice: Option<(String, Instant)>,
+    scanner: DotfileScanner,
+    watcher: Option<RootWatcher>,
+    /// Reload once the filesystem has been quiet until then.
+    reload_at: Option<Instant>,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let scanner = DotfileScanner::new();
-        let dotfiles = scanner.scan()?;
+    pub fn new(scanner: DotfileScanner) -> Result<Self> {
+        let report = scanner.scan_report()?;
+        let mut problems = collect_problems(&report);
+        let dirs = report.dirs;
+        let dotfiles = report.entries;
+        let settings = load_settings(&mut problems);
+        let (profiles, profile_problems) = load_profiles(&settings, &scanner.roots());
+        problems.extend(profile_problems);
 
-        Ok(Self {
+        let mut app = Self {
             view: View::Home,
             dotfiles,
-            selected_index: 0,
+            rows: vec![],
+            cursor: 0,
+            collapsed: HashSet::new(),
+            browse: settings.browse,
+            filter: Filter::default(),
+            filtered: vec![],
+            searching: false,
+            marked: HashSet::new(),
             should_quit: false,
-            install_output: None,
+            install_output: vec![],
             install_success: false,
-        })
+            install_job: None,
+            install_started: Instant::now(),
+            install_elapsed: Duration::ZERO,
+            dry_run: false,
+            keep_going: false,
+            install_plans: vec![],
+            batch: vec![],
+            dep_checks: vec![],
+            dep_check_job: None,
+            preview_file: 0,
+            file_view: None,
+            file_scroll: 0,
+            palette: vec![],
+            screenshot: None,
+            graphics: settings.preview.graphics.resolve(),
+            diff_lines: vec![],
+            diff_scroll: 0,
+            state: InstallState::default(),
+            statuses: HashMap::new(),
+            pending_rollback: None,
+            problems,
+            problems_scroll: 0,
+            profiles,
+            profile_cursor: 0,
+            profile_items: vec![],
+            active_profile_applied: false,
+            applying_profile: None,
+            install_origin: View::Preview,
+            problems_origin: View::Home,
+            profiles_origin: View::Home,
+            notice: None,
+            watcher: None,
+            reload_at: None,
+            scanner,
+        };
+        app.reload_state()?;
+        app.refilter();
+
+        match RootWatcher::new(&dirs) {
+            Ok(watcher) => app.watcher = Some(watcher),
+            Err(e) => app.set_notice(format!("live reload unavailable: {}", e)),
+        }
+
+        Ok(app)
+    }
+
+    /// Scans again, keeping the selection on the same entry when it still
+    /// exists. Failures end up in the footer rather than closing the TUI.
+    pub fn reload(&mut self) {
+        if let Err(e) = self.rescan() {
+            self.set_notice(format!("reload failed: {}", e));
+        }
+    }
+
+    fn rescan(&mut self) -> Result<()> {
+        let report = self.scanner.scan_report()?;
+        let current = self.current_row_key();
+        if let Some(watcher) = &mut self.watcher {
+            watcher.watch(&report.dirs);
+        }
+        self.problems = collect_problems(&report);
+        self.dotfiles = report.entries;
+        let settings = load_settings(&mut self.problems);
+        let (profiles, profile_problems) = load_profiles(&settings, &self.scanner.roots());
+        self.profiles = profiles;
+        self.problems.extend(profile_problems);
+        self.profile_cursor = self
+            .profile_cursor
+            .min(self.profiles.len().saturating_sub(1));
+        self.reload_state()?;
+        self.refilter_keeping(current);
+
+        self.set_notice(format!(
+            "reloaded ({} entr{})",
+            self.dotfiles.len(),
+            if self.dotfiles.len() == 1 { "y" } else { "ies" }
+        ));
+        Ok(())
+    }
+
+    /// Reloads after manifests change on disk, waiting for a burst of
+    /// events (an editor saving, a `git pull`) to settle first.
+    pub fn poll_changes(&mut self) {
+        if self.watcher.as_ref().is_some_and(|w| w.has_changes()) {
+            self.reload_at = Some(Instant::now() + Duration::from_millis(300));
+        }
+        if self.reload_at.is_some_and(|at| Instant::now() >= at) {
+            self.reload_at = None;
+            self.reload();
+        }
+    }
+
+    pub fn set_notice(&mut self, message: String) {
+        self.notice = Some((message, Instant::now()));
+    }
+
+    /// The footer notice, if it is recent enough to still show.
+    pub fn notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < Duration::from_secs(3))
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Re-reads the install state and recomputes every entry's status.
+    pub fn reload_state(&mut self) -> Result<()> {
+        self.state = InstallState::load()?;
+        self.statuses = self
+            .dotfiles
+            .iter()
+            .map(|entry| (entry.path.clone(), entry_status(entry, &self.state).status))
+            .collect();
+        self.active_profile_applied = self
+            .state
+            .active_profile
+            .as_deref()
+            .and_then(|name| find_profile(&self.profiles, name))
+            .is_some_and(|profile| {
+                is_applied(
+                    profile,
+                    &plan_profile(profile, &self.dotfiles, &self.state),
+                    &self.state,
+                )
+            });
+        self.plan_selected_profile();
+        Ok(())
     }
 
     pub fn next_item(&mut self) {
-        if !self.dotfiles.is_empty() {
-            self.selected_index = (self.selected_index + 1) % self.dotfiles.len();
+        if !self.rows.is_empty() {
+            self.cursor = (self.cursor + 1) % self.rows.len();
         }
     }
 
     pub fn previous_item(&mut self) {
-        if !self.dotfiles.is_empty() {
-            if self.selected_index == 0 {
-                self.selected_index = self.dotfiles.len() - 1;
+        if !self.rows.is_empty() {
+            if self.cursor == 0 {
+                self.cursor = self.rows.len() - 1;
             } else {
-                self.selected_index -= 1;
+                self.cursor -= 1;
             }
         }
     }
 
+    fn current_row_key(&self) -> Option<RowKey> {
+        self.rows
+            .get(self.cursor)
+            .map(|row| row.key(&self.dotfiles))
+    }
+
+    fn refilter(&mut self) {
+        self.refilter_keeping(self.current_row_key());
+    }
+
+    /// Recomputes the browse tree, keeping the cursor on the row with `key`
+    /// or, when that row is gone, moving it to the best match.
+    fn refilter_keeping(&mut self, key: Option<RowKey>) {
+        self.filtered = search::filter_entries(&self.dotfiles, &self.filter);
+        self.rebuild_rows();
+
+        let position = key
+            .and_then(|key| {
+                self.rows
+                    .iter()
+                    .position(|row| row.key(&self.dotfiles) == key)
+            })
+            .or_else(|| self.best_match_row());
+        self.cursor = position.unwrap_or(0);
+    }
+
+    fn rebuild_rows(&mut self) {
+        self.rows = build_rows(
+            &self.dotfiles,
+            &self.filtered,
+            &self.collapsed,
+            &self.scanner.roots(),
+            self.browse.group_by_directory,
+            self.filter.is_active(),
+        );
+    }
+
+    /// The row of the top-scoring entry, or the first entry without a query.
+    fn best_match_row(&self) -> Option<usize> {
+        self.rows.iter().position(|row| match row {
+            BrowseRow::Entry { filtered, .. } => self.filter.query.is_empty() || *filtered == 0,
+            BrowseRow::Group { .. } => false,
+        })
+    }
+
+    pub fn on_group(&self) -> bool {
+        matches!(self.rows.get(self.cursor), Some(BrowseRow::Group { .. }))
+    }
+
+    pub fn toggle_group(&mut self) {
+        let Some(BrowseRow::Group { key, collapsed, .. }) = self.rows.get(self.cursor) else {
+            return;
+        };
+        let (key, collapsed) = (key.clone(), *collapsed);
+        if collapsed {
+            self.collapsed.remove(&key);
+        } else {
+            self.collapsed.insert(key);
+        }
+        self.refilter();
+    }
+
+    /// Collapses the selected group, or jumps from an entry to its group.
+    pub fn collapse_group(&mut self) {
+        match self.rows.get(self.cursor) {
+            Some(BrowseRow::Group {
+                collapsed: false, ..
+            }) => self.toggle_group(),
+            Some(row) => {
+                let depth = row.depth();
+                if let Some(parent) = self.rows[..self.cursor]
+                    .iter()
+                    .rposition(|row| row.depth() < depth)
+                {
+                    self.cursor = parent;
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn expand_group(&mut self) {
+        if let Some(BrowseRow::Group {
+            collapsed: true, ..
+        }) = self.rows.get(self.cursor)
+        {
+            self.toggle_group();
+        }
+    }
+
+    pub fn start_search(&mut self) {
+        self.searching = true;
+    }
+
+    /// Leaves search mode, keeping the query applied.
+    pub fn finish_search(&mut self) {
+        self.searching = false;
+    }
+
+    pub fn push_search_char(&mut self, c: char) {
+        self.filter.query.push(c);
+        self.refilter();
+        // While typing, the best match is what Enter should open
+        if let Some(row) = self.best_match_row() {
+            self.cursor = row;
+        }
+    }
+
+    pub fn pop_search_char(&mut self) {
+        self.filter.query.pop();
+        self.refilter();
+    }
+
+    /// Drops the query and both chips.
+    pub fn clear_filter(&mut self) {
+        self.searching = false;
+        self.filter = Filter::default();
+        self.refilter();
+    }
+
+    pub fn cycle_category_filter(&mut self) {
+        let categories = search::categories(&self.dotfiles);
+        self.filter.category = search::cycle(&categories, self.filter.category.as_ref());
+        self.refilter();
+    }
+
+    pub fn cycle_compiler_filter(&mut self) {
+        let compilers = search::compilers(&self.dotfiles);
+        self.filter.compiler = search::cycle(&compilers, self.filter.compiler.as_ref());
+        self.refilter();
+    }
+
+    /// The entry under the cursor; `None` when the cursor is on a group.
     pub fn selected_dotfile(&self) -> Option<&DotfileEntry> {
-        self.dotfiles.get(self.selected_index)
+        match self.rows.get(self.cursor) {
+            Some(BrowseRow::Entry { index, .. }) => self.dotfiles.get(*index),
+            _ => None,
+        }
     }
 
     pub fn quit(&mut self) {
@@ -67,19 +450,450 @@ impl App {
             View::Home => View::Home,
             View::Browse => View::Home,
             View::Preview => View::Browse,
-            View::Installing => View::Preview,
+            View::Diff => View::Preview,
+            View::Installing => self.install_origin,
+            View::Problems => self.problems_origin,
+            View::Profiles => self.profiles_origin,
         };
     }
 
-    pub fn install_selected(&mut self) -> Result<()> {
-        if let Some(entry) = self.selected_dotfile() {
-            use crate::installer::install_dotfile;
+    /// Installs the selected entry. `force` skips the dependency check.
+    pub fn install_selected(&mut self, force: bool) {
+        if self.is_installing() {
+            return;
+        }
 
-            let result = install_dotfile(entry)?;
-            self.install_output = Some(result.output);
-            self.install_success = result.success;
+        if let Some(entry) = self.selected_dotfile() {
+            if self.dry_run {
+                self.install_plans = vec![plan_install(entry)];
+                self.install_success = true;
+            } else {
+                self.install_job = Some(spawn_install(entry.clone(), force));
+                self.install_plans.clear();
+                self.install_started = Instant::now();
+                self.install_elapsed = Duration::ZERO;
+            }
+            self.batch.clear();
+            self.install_output.clear();
+            self.install_origin = match self.view {
+                View::Diff => View::Diff,
+                _ => View::Preview,
+            };
             self.view = View::Installing;
         }
+    }
+
+    /// Marks or unmarks the selected entry and moves on to the next row.
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self.selected_dotfile().map(|entry| entry.path.clone()) else {
+            return;
+        };
+        if !self.marked.remove(&path) {
+            self.marked.insert(path);
+        }
+        self.next_item();
+    }
+
+    /// Installs every marked entry, dependencies first, stopping at the
+    /// first failure unless `keep_going` is set. Entries stay marked until
+    /// they install successfully.
+    pub fn install_marked(&mut self, force: bool) {
+        if self.is_installing() {
+            return;
+        }
+        if self.marked.is_empty() {
+            self.set_notice("nothing marked (space marks an entry)".to_string());
+            return;
+        }
+
+        let entries: Vec<DotfileEntry> = self
+            .dotfiles
+            .iter()
+            .filter(|entry| self.marked.contains(&entry.path))
+            .cloned()
+            .collect();
+        let entries = match install_order(entries) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.set_notice(e);
+                return;
+            }
+        };
+
+        if self.dry_run {
+            self.install_plans = entries.iter().map(plan_install).collect();
+            self.install_success = true;
+            self.batch.clear();
+        } else {
+            self.start_batch(entries, force);
+        }
+        self.install_output.clear();
+        self.install_origin = self.view;
+        self.view = View::Installing;
+    }
+
+    /// Starts installing `entries` in order, honouring `keep_going`.
+    fn start_batch(&mut self, entries: Vec<DotfileEntry>, force: bool) {
+        self.batch = entries
+            .iter()
+            .map(|entry| BatchItem {
+                name: entry.config.name.clone(),
+                path: entry.path.clone(),
+                outcome: EntryOutcome::Pending,
+            })
+            .collect();
+        self.install_job = Some(spawn_batch_install(entries, force, self.keep_going));
+        self.install_plans.clear();
+        self.install_started = Instant::now();
+        self.install_elapsed = Duration::ZERO;
+    }
+
+    pub fn is_installing(&self) -> bool {
+        self.install_job.is_some()
+    }
+
+    /// Drains output from the background install. Called once per frame.
+    pub fn poll_install(&mut self) {
+        let Some(job) = &self.install_job else {
+            return;
+        };
+
+        self.install_elapsed = self.install_started.elapsed();
+        let mut finished = None;
+        while let Ok(event) = job.events.try_recv() {
+            match event {
+                InstallEvent::Line(line) => self.install_output.push(line),
+                InstallEvent::Entry(path, outcome) => {
+                    if let Some(item) = self.batch.iter_mut().find(|item| item.path == path) {
+                        if outcome == EntryOutcome::Installed {
+                            self.marked.remove(&item.path);
+                        }
+                        item.outcome = outcome;
+                    }
+                }
+                InstallEvent::Finished(result) => finished = Some(result),
+            }
+        }
+
+        if let Some(result) = finished {
+            self.install_job = None;
+            match result {
+                Ok(success) => self.install_success = success,
+                Err(e) => {
+                    self.install_output.push(format!("error: {}", e));
+                    self.install_success = false;
+                }
+            }
+            // The install itself is done, so state errors are only reported
+            if let Some(profile) = self.applying_profile.take() {
+                if self.install_success {
+                    let line = match record_applied(&profile) {
+                        Ok(()) => format!("profile {} is active", profile),
+                        Err(e) => format!("error: could not record profile {}: {:#}", profile, e),
+                    };
+                    self.install_output.push(line);
+                }
+            }
+            if let Err(e) = self.reload_state() {
+                self.install_output
+                    .push(format!("error: could not reload install state: {:#}", e));
+            }
+        }
+    }
+
+    pub fn cancel_install(&mut self) {
+        if let Some(job) = &self.install_job {
+            job.cancel();
+        }
+    }
+
+    /// Checks the selected entry's dependencies and switches to the Preview view.
+    pub fn open_preview(&mut self) {
+        if let Some(entry) = self.selected_dotfile() {
+            let palette = entry_palette(entry);
+            let screenshot = entry
+                .config
+                .screenshot
+                .as_ref()
+                .map(|file| Screenshot::load(&entry.path.join(file)));
+            let dep_checks = locate_dependencies(&entry.config.dependencies);
+            self.dep_check_job = spawn_version_checks(&dep_checks, entry);
+            self.dep_checks = dep_checks;
+            self.palette = palette;
+            self.screenshot = screenshot;
+            self.view = View::Preview;
+            self.preview_file = 0;
+            self.load_preview_file();
+        }
+    }
+
+    /// Picks up the preview's version checks once they are done.
+    pub fn poll_dep_checks(&mut self) {
+        let Some(job) = &self.dep_check_job else {
+            return;
+        };
+        match job.try_recv() {
+            Ok(checks) => {
+                self.dep_checks = checks;
+                self.dep_check_job = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.dep_check_job = None,
+        }
+    }
+
+    pub fn is_checking_dependencies(&self) -> bool {
+        self.dep_check_job.is_some()
+    }
+
+    /// Moves the Files panel selection and loads the newly selected file.
+    pub fn select_preview_file(&mut self, delta: i32) {
+        let Some(entry) = self.selected_dotfile() else {
+            return;
+        };
+        let max = entry.config.files.len().saturating_sub(1) as i32;
+        let file = (self.preview_file as i32 + delta).clamp(0, max) as usize;
+        if file != self.preview_file {
+            self.preview_file = file;
+            self.load_preview_file();
+        }
+    }
+
+    fn load_preview_file(&mut self) {
+        self.file_scroll = 0;
+        self.file_view = self.selected_dotfile().and_then(|entry| {
+            let file = entry.config.files.get(self.preview_file)?;
+            Some(load_file(&entry.path.join(file)))
+        });
+    }
+
+    pub fn scroll_file(&mut self, delta: i32) {
+        let lines = self.file_view.as_ref().map_or(0, |view| view.lines.len());
+        let max = lines.saturating_sub(1) as i32;
+        self.file_scroll = (self.file_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    pub fn toggle_dry_run(&mut self) {
+        self.dry_run = !self.dry_run;
+    }
+
+    pub fn toggle_keep_going(&mut self) {
+        self.keep_going = !self.keep_going;
+    }
+
+    /// Computes the diff for the selected entry and switches to the Diff view.
+    pub fn open_diff(&mut self) {
+        if let Some(entry) = self.selected_dotfile() {
+            self.diff_lines = diff_entry(entry);
+            self.diff_scroll = 0;
+            self.view = View::Diff;
+        }
+    }
+
+    pub fn scroll_diff(&mut self, delta: i32) {
+        let max = self.diff_lines.len().saturating_sub(1) as i32;
+        self.diff_scroll = (self.diff_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    pub fn open_profiles(&mut self) {
+        if self.view != View::Profiles {
+            self.profiles_origin = self.view;
+        }
+        self.profile_cursor = self
+            .profile_cursor
+            .min(self.profiles.len().saturating_sub(1));
+        self.plan_selected_profile();
+        self.view = View::Profiles;
+    }
+
+    pub fn selected_profile(&self) -> Option<&Profile> {
+        self.profiles.get(self.profile_cursor)
+    }
+
+    /// Whether the profile was applied last and nothing has drifted since.
+    pub fn is_profile_applied(&self, profile: &Profile) -> bool {
+        self.active_profile_applied && self.state.active_profile.as_deref() == Some(&profile.name)
+    }
+
+    pub fn move_profile_cursor(&mut self, delta: i32) {
+        if self.profiles.is_empty() {
+            return;
+        }
+        let max = self.profiles.len() as i32 - 1;
+        self.profile_cursor = (self.profile_cursor as i32 + delta).clamp(0, max) as usize;
+        self.plan_selected_profile();
+    }
+
+    fn plan_selected_profile(&mut self) {
+        self.profile_items = match self.profiles.get(self.profile_cursor) {
+            Some(profile) => plan_profile(profile, &self.dotfiles, &self.state),
+            None => vec![],
+        };
+    }
+
+    /// Installs the selected profile's entries that are not in place yet,
+    /// dependencies first, and records the profile once all succeed.
+    pub fn apply_selected_profile(&mut self, force: bool) -> Result<()> {
+        if self.is_installing() {
+            return Ok(());
+        }
+        let Some(profile) = self.selected_profile().map(|p| p.name.clone()) else {
+            return Ok(());
+        };
+
+        let unknown: Vec<&str> = self
+            .profile_items
+            .iter()
+            .filter(|item| item.action == ProfileAction::Unknown)
+            .map(|item| item.name.as_str())
+            .collect();
+        if !unknown.is_empty() {
+            self.set_notice(format!("unknown entries: {}", unknown.join(", ")));
+            return Ok(());
+        }
+
+        let pending: Vec<DotfileEntry> = self
+            .profile_items
+            .iter()
+            .filter(|item| item.action == ProfileAction::Install)
+            .filter_map(|item| item.entry.clone())
+            .collect();
+        let entries = match install_order(pending) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.set_notice(e);
+                return Ok(());
+            }
+        };
+
+        if entries.is_empty() {
+            if !self.dry_run {
+                record_applied(&profile)?;
+                self.reload_state()?;
+            }
+            self.set_notice(format!("{} is already in place", profile));
+            return Ok(());
+        }
+
+        if self.dry_run {
+            self.install_plans = entries.iter().map(plan_install).collect();
+            self.install_success = true;
+            self.batch.clear();
+        } else {
+            self.start_batch(entries, force);
+            self.applying_profile = Some(profile);
+        }
+        self.install_output.clear();
+        self.install_origin = self.view;
+        self.view = View::Installing;
+        Ok(())
+    }
+
+    pub fn open_problems(&mut self) {
+        self.problems_origin = self.view;
+        self.problems_scroll = 0;
+        self.view = View::Problems;
+    }
+
+    pub fn scroll_problems(&mut self, delta: i32) {
+        let max = self.problems.len().saturating_sub(1) as i32;
+        self.problems_scroll = (self.problems_scroll as i32 + delta).clamp(0, max) as u16;
+    }
+
+    /// Opens the confirm dialog for the most recent backup snapshot.
+    pub fn rollback_last(&mut self) {
+        let (snapshot, warnings) = backup::find_snapshot(None, None);
+        if let Some(warning) = warnings.into_iter().next() {
+            self.set_notice(warning);
+        }
+        match snapshot {
+            Some(snapshot) => self.pending_rollback = Some(snapshot),
+            None => {
+                self.install_plans.clear();
+                self.batch.clear();
+                self.install_output = vec!["No backups to roll back to.".to_string()];
+                self.install_success = false;
+                self.install_origin = self.view;
+                self.view = View::Installing;
+            }
+        }
+    }
+
+    pub fn confirm_rollback(&mut self) -> Result<()> {
+        let Some(snapshot) = self.pending_rollback.take() else {
+            return Ok(());
+        };
+
+        let results = backup::restore(&snapshot);
+        let restored = results.iter().all(|r| r.result.is_ok());
+        // A partial restore leaves the install in place, so keep its record
+        if restored {
+            self.state.forget(&snapshot.manifest.path);
+            self.state.save()?;
+            self.reload_state()?;
+        }
+        let mut lines = vec![format!(
+            "Rolled back {} to {}",
+            snapshot.manifest.entry, snapshot.manifest.timestamp
+        )];
+        for file in &results {
+            lines.push(match &file.result {
+                Ok(action) => format!("✓ {} {}", action, file.path.display()),
+                Err(e) => format!("✗ {}: {}", file.path.display(), e),
+            });
+        }
+        if !restored {
+            let failed = results.iter().filter(|r| r.result.is_err()).count();
+            lines.push(format!(
+                "{} file(s) could not be restored; the snapshot and install record were kept",
+                failed
+            ));
+        }
+
+        self.install_plans.clear();
+        self.batch.clear();
+        self.install_success = restored;
+        self.install_output = lines;
+        self.install_origin = self.view;
+        self.view = View::Installing;
         Ok(())
     }
+
+    pub fn cancel_rollback(&mut self) {
+        self.pending_rollback = None;
+    }
+}
+
+/// The user's settings, or the defaults when they fail to load (possible
+/// with `--path`), with the reason added to `problems`.
+fn load_settings(problems: &mut Vec<ManifestProblem>) -> Settings {
+    Settings::load().unwrap_or_else(|e| {
+        let problem = e
+            .downcast::<ManifestProblem>()
+            .unwrap_or_else(|e| ManifestProblem::new(&Settings::path(), e.to_string()));
+        problems.push(problem);
+        Settings::default()
+    })
+}
+
+/// Runs `--version` for the constraints `locate_dependencies` left
+/// unchecked, off the UI thread since a program may take a while.
+fn spawn_version_checks(
+    checks: &[DepCheck],
+    entry: &DotfileEntry,
+) -> Option<Receiver<Vec<DepCheck>>> {
+    let unchecked = checks
+        .iter()
+        .any(|dep| matches!(dep.status, DepStatus::Unchecked { .. }));
+    if !unchecked {
+        return None;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let specs = entry.config.dependencies.clone();
+    thread::spawn(move || {
+        // The receiver is gone if another entry was opened meanwhile
+        let _ = tx.send(check_dependencies(&specs));
+    });
+    Some(rx)
 }
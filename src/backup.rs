@@ -0,0 +1,402 @@
+use crate::config::{DeployTarget, DotfileEntry};
+use crate::paths;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Snapshots kept per entry; older ones are deleted after each new one.
+const KEEP_SNAPSHOTS: usize = 10;
+
+/// What was at a target path before Dotwell replaced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackupKind {
+    File,
+    Directory,
+    Symlink {
+        points_to: PathBuf,
+    },
+    /// Nothing existed there; restoring means removing what was deployed.
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    pub original: PathBuf,
+    /// Location of the saved copy, relative to the snapshot directory.
+    pub stored: Option<PathBuf>,
+    #[serde(flatten)]
+    pub kind: BackupKind,
+    pub mode: Option<u32>,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub entry: String,
+    /// Directory of the entry, which tells apart entries sharing a name.
+    /// Empty in older manifests.
+    #[serde(default)]
+    pub path: PathBuf,
+    pub timestamp: String,
+    /// Nanoseconds since the Unix epoch. Orders snapshots taken within the
+    /// same second, e.g. by a batch install; zero in older manifests.
+    #[serde(default)]
+    pub created_ns: u128,
+    pub files: Vec<BackedUpFile>,
+}
+
+/// A snapshot directory together with its parsed manifest.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub dir: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+/// Outcome of restoring one file from a snapshot.
+pub struct RestoredFile {
+    pub path: PathBuf,
+    pub result: Result<&'static str, String>,
+}
+
+/// Directory holding every snapshot taken for the entry at `entry_path`,
+/// named after its directory plus a hash of the full path.
+pub fn entry_backup_dir(entry_path: &Path) -> PathBuf {
+    let name = entry_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(entry_path.to_string_lossy().as_bytes())
+    );
+    paths::dotwell_state_dir()
+        .join("backups")
+        .join(format!("{}-{}", slug(&name), &hash[..12]))
+}
+
+/// Whether the target already holds exactly what deploying would put there,
+/// so backing it up would only save a copy of the source.
+pub fn matches_source(target: &DeployTarget) -> bool {
+    let is_link = fs::symlink_metadata(&target.dest).is_ok_and(|meta| meta.is_symlink());
+    match (checksum(&target.dest), checksum(&target.source)) {
+        (Ok(dest), Ok(source)) => !is_link && dest == source,
+        _ => false,
+    }
+}
+
+/// Saves whatever currently sits at each target into a new timestamped
+/// snapshot, then prunes the entry's oldest snapshots beyond
+/// `KEEP_SNAPSHOTS`. Targets matching their source are left out; returns
+/// `None` when no target is left to protect.
+pub fn snapshot(entry: &DotfileEntry, targets: &[DeployTarget]) -> Result<Option<Snapshot>> {
+    let targets: Vec<&DeployTarget> = targets.iter().filter(|t| !matches_source(t)).collect();
+    if targets.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = new_snapshot_dir(&entry_backup_dir(&entry.path))?;
+    let mut files = vec![];
+
+    for (i, target) in targets.iter().enumerate() {
+        let original = target.dest.clone();
+        let Ok(meta) = fs::symlink_metadata(&original) else {
+            files.push(BackedUpFile {
+                original,
+                stored: None,
+                kind: BackupKind::Missing,
+                mode: None,
+                sha256: None,
+            });
+            continue;
+        };
+
+        let mode = Some(meta.permissions().mode());
+        if meta.file_type().is_symlink() {
+            files.push(BackedUpFile {
+                kind: BackupKind::Symlink {
+                    points_to: fs::read_link(&original)?,
+                },
+                original,
+                stored: None,
+                mode,
+                sha256: None,
+            });
+            continue;
+        }
+
+        // Prefix with the index so two targets sharing a file name don't collide
+        let name = original
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stored = PathBuf::from(format!("{}-{}", i, name));
+        copy_tree(&original, &dir.join(&stored))?;
+
+        files.push(BackedUpFile {
+            kind: if meta.is_dir() {
+                BackupKind::Directory
+            } else {
+                BackupKind::File
+            },
+            sha256: Some(checksum(&original)?),
+            original,
+            stored: Some(stored),
+            mode,
+        });
+    }
+
+    let manifest = BackupManifest {
+        entry: entry.config.name.clone(),
+        path: entry.path.clone(),
+        timestamp: dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        created_ns: now_ns(),
+        files,
+    };
+    fs::write(
+        dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    let (snapshots, _) = list_snapshots(Some(&entry.path));
+    let excess = snapshots.len().saturating_sub(KEEP_SNAPSHOTS);
+    for old in &snapshots[..excess] {
+        let _ = fs::remove_dir_all(&old.dir);
+    }
+
+    Ok(Some(Snapshot { dir, manifest }))
+}
+
+/// Snapshots for the entry at `entry_path`, or for every entry when it is
+/// `None`, ordered oldest first. Snapshots whose manifest can't be parsed
+/// are skipped and reported as warnings.
+pub fn list_snapshots(entry_path: Option<&Path>) -> (Vec<Snapshot>, Vec<String>) {
+    let entry_dirs = match entry_path {
+        Some(path) => vec![entry_backup_dir(path)],
+        None => match fs::read_dir(paths::dotwell_state_dir().join("backups")) {
+            Ok(read_dir) => read_dir.flatten().map(|e| e.path()).collect(),
+            Err(_) => vec![],
+        },
+    };
+
+    let mut snapshots = vec![];
+    let mut warnings = vec![];
+    for entry_dir in entry_dirs {
+        let Ok(read_dir) = fs::read_dir(&entry_dir) else {
+            continue;
+        };
+        for dir in read_dir.flatten().map(|e| e.path()) {
+            let Ok(content) = fs::read_to_string(dir.join(MANIFEST_FILE)) else {
+                continue;
+            };
+            match serde_json::from_str(&content) {
+                Ok(manifest) => snapshots.push(Snapshot { manifest, dir }),
+                Err(e) => warnings.push(format!("skipping snapshot {}: {}", dir.display(), e)),
+            }
+        }
+    }
+
+    snapshots.sort_by(|a, b| {
+        (a.manifest.created_ns, &a.manifest.timestamp)
+            .cmp(&(b.manifest.created_ns, &b.manifest.timestamp))
+    });
+    (snapshots, warnings)
+}
+
+/// The newest snapshot, or the one whose timestamp matches `at`, along with
+/// the warnings from `list_snapshots`.
+pub fn find_snapshot(
+    entry_path: Option<&Path>,
+    at: Option<&str>,
+) -> (Option<Snapshot>, Vec<String>) {
+    let (snapshots, warnings) = list_snapshots(entry_path);
+    let snapshot = match at {
+        Some(at) => snapshots.into_iter().find(|s| s.manifest.timestamp == at),
+        None => snapshots.into_iter().last(),
+    };
+    (snapshot, warnings)
+}
+
+/// Puts every file recorded in the snapshot back where it was. The snapshot
+/// is deleted once everything is restored, so the next rollback goes one
+/// step further back.
+pub fn restore(snapshot: &Snapshot) -> Vec<RestoredFile> {
+    let results: Vec<RestoredFile> = snapshot
+        .manifest
+        .files
+        .iter()
+        .map(|file| RestoredFile {
+            path: file.original.clone(),
+            result: restore_file(&snapshot.dir, file).map_err(|e| e.to_string()),
+        })
+        .collect();
+
+    if results.iter().all(|r| r.result.is_ok()) {
+        let _ = fs::remove_dir_all(&snapshot.dir);
+    }
+    results
+}
+
+fn restore_file(snapshot_dir: &Path, file: &BackedUpFile) -> Result<&'static str> {
+    let original = &file.original;
+    match fs::symlink_metadata(original) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(original)?,
+        Ok(_) => fs::remove_file(original)?,
+        Err(_) => {}
+    }
+
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match &file.kind {
+        BackupKind::Missing => return Ok("removed"),
+        BackupKind::Symlink { points_to } => {
+            std::os::unix::fs::symlink(points_to, original)?;
+            return Ok("relinked");
+        }
+        BackupKind::File | BackupKind::Directory => {
+            let stored = file
+                .stored
+                .as_ref()
+                .ok_or_else(|| color_eyre::eyre::eyre!("snapshot has no stored copy"))?;
+            copy_tree(&snapshot_dir.join(stored), original)?;
+        }
+    }
+
+    if let Some(mode) = file.mode {
+        fs::set_permissions(original, fs::Permissions::from_mode(mode))?;
+    }
+    Ok("restored")
+}
+
+/// SHA-256 of a file, or of every file below a directory in path order.
+pub fn checksum(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hash_into(path, path, true, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Checksum of `files` inside `dir`, e.g. an entry's declared sources.
+/// Symlinks are hashed by where they point rather than followed, so a
+/// link into a large tree (like a nix `result`) stays cheap.
+pub fn checksum_files(dir: &Path, files: &[String]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for file in files {
+        hash_into(dir, &dir.join(file), false, &mut hasher)?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_into(root: &Path, path: &Path, follow_links: bool, hasher: &mut Sha256) -> Result<()> {
+    let metadata = if follow_links {
+        fs::metadata(path)?
+    } else {
+        fs::symlink_metadata(path)?
+    };
+    if metadata.is_dir() {
+        let mut children: Vec<PathBuf> = fs::read_dir(path)?.flatten().map(|e| e.path()).collect();
+        children.sort();
+        for child in children {
+            hash_into(root, &child, follow_links, hasher)?;
+        }
+        return Ok(());
+    }
+
+    if let Ok(relative) = path.strip_prefix(root) {
+        hasher.update(relative.to_string_lossy().as_bytes());
+    }
+    if metadata.is_symlink() {
+        hasher.update(fs::read_link(path)?.to_string_lossy().as_bytes());
+    } else {
+        hasher.update(fs::read(path)?);
+    }
+    Ok(())
+}
+
+fn copy_tree(source: &Path, dest: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(dest)?;
+        for child in fs::read_dir(source)?.flatten() {
+            copy_tree(&child.path(), &dest.join(child.file_name()))?;
+        }
+    } else {
+        fs::copy(source, dest)?;
+    }
+    Ok(())
+}
+
+fn new_snapshot_dir(entry_dir: &Path) -> Result<PathBuf> {
+    let stamp = timestamp();
+    let mut dir = entry_dir.join(&stamp);
+    let mut n = 1;
+    while dir.exists() {
+        dir = entry_dir.join(format!("{}-{}", stamp, n));
+        n += 1;
+    }
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Nanoseconds since the Unix epoch, for ordering events within a second.
+pub fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+/// Current UTC time as `YYYYMMDDTHHMMSS`, which sorts chronologically.
+pub fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let rem = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
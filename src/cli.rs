@@ -0,0 +1,980 @@
+use crate::backup::{find_snapshot, restore};
+use crate::config::{
+    get_compiler_name, get_deploy_name, DotfileConfig, DotfileEntry, ManifestProblem,
+};
+use crate::deps::{check_dependencies, DepCheck, DepStatus};
+use crate::diff::{diff_entry, DiffLine};
+use crate::installer::{
+    install_order, plan_install, spawn_batch_install, spawn_install, DeployAction, EntryOutcome,
+    InstallEvent, InstallPlan,
+};
+use crate::palette::entry_palette;
+use crate::profile::{
+    find_profile, is_applied, load_profiles, plan_profile, record_applied, Profile, ProfileAction,
+};
+use crate::scanner::{DotfileScanner, ScanReport};
+use crate::settings::Settings;
+use crate::state::{EntryState, InstallState};
+use crate::status::{entry_status, get_status_name, FileReport, Status};
+use crate::validate::collect_problems;
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// The operation ran but did not succeed (failed install, unrestorable file, ...).
+const EXIT_FAILED: u8 = 1;
+/// Bad arguments or an unknown entry; clap uses the same code for usage errors.
+const EXIT_USAGE: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "dwell",
+    version,
+    about = "A TUI for managing dotfiles and rice themes"
+)]
+#[command(after_help = "Run without a command to open the TUI.\n\n\
+Exit codes: 0 success, 1 the operation failed, 2 bad usage or unknown entry.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Same as `dwell list`
+    #[arg(short, long, hide = true)]
+    pub list: bool,
+
+    /// Output format for list, show, status, install and validate
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    /// Shorthand for `--format json`
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Scan this directory instead of the configured roots (repeatable)
+    #[arg(long = "path", global = true, value_name = "DIR")]
+    pub paths: Vec<PathBuf>,
+
+    /// Walk every directory instead of trusting the scan cache
+    #[arg(long, global = true)]
+    pub rescan: bool,
+}
+
+impl Cli {
+    pub fn output_format(&self) -> Format {
+        if self.json {
+            Format::Json
+        } else {
+            self.format
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Toml,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List every dotfile configuration found
+    List,
+    /// Show everything known about one entry
+    Show { name: String },
+    /// Install one or more entries, dependencies first
+    Install {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Print what would happen without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Install even if dependencies are not satisfied
+        #[arg(long)]
+        force: bool,
+        /// Carry on with the other entries after one fails
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// Install whatever a profile needs to become the current look
+    Apply {
+        profile: String,
+        /// Print what would happen without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Install even if dependencies are not satisfied
+        #[arg(long)]
+        force: bool,
+        /// Carry on with the other entries after one fails
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// List the profiles and whether each is applied
+    Profiles,
+    /// Show drift of installed entries
+    Status,
+    /// Diff deployed files against the entry's files
+    Diff { name: String },
+    /// Restore the most recent (or chosen) backup snapshot
+    Rollback {
+        /// Only consider snapshots of this entry
+        entry: Option<String>,
+        /// Snapshot timestamp to restore
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Check manifests for parse errors and missing files
+    Validate {
+        /// A manifest or a directory to scan instead of the usual locations
+        path: Option<PathBuf>,
+    },
+}
+
+/// An entry as it appears in `list` and `show` output.
+#[derive(Serialize)]
+struct EntryOutput<'a> {
+    #[serde(flatten)]
+    config: &'a DotfileConfig,
+    path: &'a Path,
+    active: bool,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    installed: Option<&'a EntryState>,
+}
+
+impl<'a> EntryOutput<'a> {
+    fn new(entry: &'a DotfileEntry, state: &'a InstallState) -> Self {
+        Self {
+            config: &entry.config,
+            path: &entry.path,
+            active: state.is_active(entry),
+            status: entry_status(entry, state).status,
+            installed: state.get(entry),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EntryList<'a> {
+    entries: Vec<EntryOutput<'a>>,
+}
+
+#[derive(Serialize)]
+struct StatusOutput<'a> {
+    name: &'a str,
+    category: &'a str,
+    path: &'a Path,
+    active: bool,
+    status: Status,
+    installed_at: &'a str,
+    files: Vec<FileReport>,
+}
+
+#[derive(Serialize)]
+struct PlanList {
+    plans: Vec<InstallPlan>,
+}
+
+#[derive(Serialize)]
+struct StatusList<'a> {
+    entries: Vec<StatusOutput<'a>>,
+}
+
+#[derive(Serialize)]
+struct BatchEntryOutput {
+    entry: String,
+    path: PathBuf,
+    #[serde(flatten)]
+    outcome: EntryOutcome,
+    output: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    /// Profile entries that were already in place.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    up_to_date: Vec<String>,
+    success: bool,
+    entries: Vec<BatchEntryOutput>,
+}
+
+#[derive(Serialize)]
+struct ProfileOutput<'a> {
+    #[serde(flatten)]
+    profile: &'a Profile,
+    active: bool,
+    to_install: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unknown: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ProfileList<'a> {
+    profiles: Vec<ProfileOutput<'a>>,
+}
+
+#[derive(Serialize)]
+struct ValidateOutput<'a> {
+    entries: usize,
+    problems: &'a [ManifestProblem],
+}
+
+/// Prints `value` as JSON or TOML. Table output is printed by each command.
+fn emit<T: Serialize>(value: &T, format: Format) -> Result<()> {
+    match format {
+        Format::Table => {}
+        Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Toml => print!("{}", toml::to_string(value)?),
+    }
+    Ok(())
+}
+
+/// Scanner for `--path` directories, or the configured roots when none were given.
+pub fn scanner(paths: &[PathBuf], rescan: bool) -> Result<DotfileScanner> {
+    let scanner = if paths.is_empty() {
+        DotfileScanner::new()?
+    } else {
+        DotfileScanner::with_paths(paths.to_vec())?
+    };
+    Ok(if rescan { scanner.rescan() } else { scanner })
+}
+
+/// Runs a subcommand, reporting errors on stderr.
+pub fn run(command: Command, format: Format, cli: &Cli) -> ExitCode {
+    let scanner = scanner(&cli.paths, cli.rescan).inspect(|scanner| {
+        if let Some(warning) = scanner.warning() {
+            eprintln!("warning: {}", warning);
+        }
+    });
+    let result = scanner.and_then(|scanner| match command {
+        Command::List => list_dotfiles(&scanner, format),
+        Command::Show { name } => show(&scanner, &name, format),
+        Command::Install {
+            names,
+            dry_run,
+            force,
+            keep_going,
+        } => match names.as_slice() {
+            // Structured output keeps the batch shape however many names are given
+            [name] if format == Format::Table => install(&scanner, name, dry_run, force),
+            _ => install_batch(&scanner, &names, dry_run, force, keep_going, format),
+        },
+        Command::Apply {
+            profile,
+            dry_run,
+            force,
+            keep_going,
+        } => apply(&scanner, &profile, dry_run, force, keep_going, format),
+        Command::Profiles => list_profiles(&scanner, format),
+        Command::Status => print_status(&scanner, format),
+        Command::Diff { name } => print_diff(&scanner, &name),
+        Command::Rollback { entry, to } => rollback(&scanner, entry.as_deref(), to.as_deref()),
+        Command::Validate { path } => validate(&scanner, path.as_deref(), format),
+    });
+
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        ExitCode::from(EXIT_FAILED)
+    })
+}
+
+/// Looks up an entry by name, case-insensitively.
+fn find_entry<'a>(dotfiles: &'a [DotfileEntry], name: &str) -> Option<&'a DotfileEntry> {
+    dotfiles
+        .iter()
+        .find(|entry| entry.config.name.eq_ignore_ascii_case(name))
+}
+
+fn unknown_entry(name: &str) -> ExitCode {
+    eprintln!("error: no dotfile named '{}' (see `dwell list`)", name);
+    ExitCode::from(EXIT_USAGE)
+}
+
+fn list_dotfiles(scanner: &DotfileScanner, format: Format) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+
+    if format != Format::Table {
+        let state = InstallState::load()?;
+        let entries = dotfiles
+            .iter()
+            .map(|entry| EntryOutput::new(entry, &state))
+            .collect();
+        emit(&EntryList { entries }, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if dotfiles.is_empty() {
+        println!("No dotfiles found.");
+        println!("\nCreate a dotwell.toml or dotwell.json file in your dotfiles directory.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!(
+        "Found {} dotfile configuration{}:\n",
+        dotfiles.len(),
+        if dotfiles.len() == 1 { "" } else { "s" }
+    );
+
+    for (i, entry) in dotfiles.iter().enumerate() {
+        let compiler = get_compiler_name(&entry.config.compiler);
+        println!("{}. {} [{}]", i + 1, entry.config.name, compiler);
+        println!("   {}", entry.config.description);
+        println!("   Path: {}", entry.path.display());
+        println!("   Category: {}", entry.config.category);
+        println!("   Dependencies: {}", entry.config.dependencies.join(", "));
+        println!();
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show(scanner: &DotfileScanner, name: &str, format: Format) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+    let Some(entry) = find_entry(&dotfiles, name) else {
+        return Ok(unknown_entry(name));
+    };
+    let state = InstallState::load()?;
+
+    if format != Format::Table {
+        emit(&EntryOutput::new(entry, &state), format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("Name:        {}", entry.config.name);
+    println!("Description: {}", entry.config.description);
+    println!("Category:    {}", entry.config.category);
+    println!("Compiler:    {}", get_compiler_name(&entry.config.compiler));
+    println!("Deploy:      {}", get_deploy_name(entry.config.deploy));
+    println!("Path:        {}", entry.path.display());
+    if !entry.config.requires.is_empty() {
+        println!("Requires:    {}", entry.config.requires.join(", "));
+    }
+
+    println!("\nDependencies:");
+    print_dependencies(&check_dependencies(&entry.config.dependencies));
+
+    println!("\nFiles:");
+    for file in &entry.config.files {
+        match entry.config.targets.get(file) {
+            Some(target) => println!("  {} -> {}", file, target),
+            None => println!("  {}", file),
+        }
+    }
+
+    let palette = entry_palette(entry);
+    if !palette.is_empty() {
+        println!("\nPalette:");
+        for swatch in &palette {
+            match &swatch.name {
+                Some(name) => println!("  {} {}", swatch.hex(), name),
+                None => println!("  {}", swatch.hex()),
+            }
+        }
+    }
+
+    println!();
+    match state.get(entry) {
+        Some(installed) => {
+            let report = entry_status(entry, &state);
+            println!(
+                "Installed:   {}{}",
+                installed.installed_at,
+                if installed.success { "" } else { " (failed)" }
+            );
+            println!("Source:      {}", installed.source);
+            println!("Status:      {}", get_status_name(report.status));
+            if state.is_active(entry) {
+                println!("Active in category '{}'", entry.config.category);
+            }
+        }
+        None => println!("Not installed"),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Installs a single entry, streaming its log.
+fn install(scanner: &DotfileScanner, name: &str, dry_run: bool, force: bool) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+    let Some(entry) = find_entry(&dotfiles, name) else {
+        return Ok(unknown_entry(name));
+    };
+
+    if dry_run {
+        print_plan(&plan_install(entry));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut success = false;
+    let job = spawn_install(entry.clone(), force);
+    for event in job.events {
+        match event {
+            InstallEvent::Line(line) => println!("{}", line),
+            InstallEvent::Entry(..) => {}
+            InstallEvent::Finished(Ok(ok)) => success = ok,
+            InstallEvent::Finished(Err(e)) => eprintln!("error: {}", e),
+        }
+    }
+
+    if !success {
+        eprintln!("error: installing {} failed", entry.config.name);
+        return Ok(ExitCode::from(EXIT_FAILED));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Installs several entries in dependency order and sums up how each went.
+fn install_batch(
+    scanner: &DotfileScanner,
+    names: &[String],
+    dry_run: bool,
+    force: bool,
+    keep_going: bool,
+    format: Format,
+) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+    let mut entries: Vec<DotfileEntry> = vec![];
+    for name in names {
+        let Some(entry) = find_entry(&dotfiles, name) else {
+            return Ok(unknown_entry(name));
+        };
+        if !entries.iter().any(|e| e.path == entry.path) {
+            entries.push(entry.clone());
+        }
+    }
+    let entries = match install_order(entries) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+    };
+
+    if dry_run {
+        emit_plans(&entries, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let results = run_batch(entries, force, keep_going, format);
+    let success = batch_succeeded(&results);
+    if format != Format::Table {
+        emit(
+            &BatchOutput {
+                profile: None,
+                up_to_date: vec![],
+                success,
+                entries: results,
+            },
+            format,
+        )?;
+    } else {
+        print_batch_summary(&results);
+    }
+
+    if !success {
+        eprintln!("error: not every entry was installed");
+        return Ok(ExitCode::from(EXIT_FAILED));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn emit_plans(entries: &[DotfileEntry], format: Format) -> Result<()> {
+    let plans: Vec<InstallPlan> = entries.iter().map(plan_install).collect();
+    match format {
+        Format::Table => {
+            for (i, plan) in plans.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                print_plan(plan);
+            }
+        }
+        _ => emit(&PlanList { plans }, format)?,
+    }
+    Ok(())
+}
+
+/// Installs `entries` in the given order. Table output streams the log;
+/// other formats collect it per entry.
+fn run_batch(
+    entries: Vec<DotfileEntry>,
+    force: bool,
+    keep_going: bool,
+    format: Format,
+) -> Vec<BatchEntryOutput> {
+    let mut results: Vec<BatchEntryOutput> = entries
+        .iter()
+        .map(|entry| BatchEntryOutput {
+            entry: entry.config.name.clone(),
+            path: entry.path.clone(),
+            outcome: EntryOutcome::Pending,
+            output: vec![],
+        })
+        .collect();
+    let mut current = 0;
+    let job = spawn_batch_install(entries, force, keep_going);
+    for event in job.events {
+        match event {
+            InstallEvent::Line(line) if format == Format::Table => println!("{}", line),
+            InstallEvent::Line(line) => results[current].output.push(line),
+            InstallEvent::Entry(path, outcome) => {
+                if let Some(index) = results.iter().position(|r| r.path == path) {
+                    current = index;
+                    results[index].outcome = outcome;
+                }
+            }
+            InstallEvent::Finished(Ok(_)) => {}
+            InstallEvent::Finished(Err(e)) => eprintln!("error: {}", e),
+        }
+    }
+    results
+}
+
+fn batch_succeeded(results: &[BatchEntryOutput]) -> bool {
+    results
+        .iter()
+        .all(|result| result.outcome == EntryOutcome::Installed)
+}
+
+fn print_batch_summary(results: &[BatchEntryOutput]) {
+    println!("\nSummary:");
+    for result in results {
+        match &result.outcome {
+            EntryOutcome::Installed => println!("  ✓ {}", result.entry),
+            EntryOutcome::Skipped(reason) => {
+                println!("  - {} (skipped: {})", result.entry, reason)
+            }
+            _ => println!("  ✗ {}", result.entry),
+        }
+    }
+}
+
+/// Loads the profiles, warning about profile files that fail to parse.
+fn profiles(scanner: &DotfileScanner) -> Result<Vec<Profile>> {
+    let (profiles, problems) = load_profiles(&Settings::load()?, &scanner.roots());
+    for problem in problems {
+        eprintln!("warning: {}", problem);
+    }
+    Ok(profiles)
+}
+
+fn list_profiles(scanner: &DotfileScanner, format: Format) -> Result<ExitCode> {
+    let profiles = profiles(scanner)?;
+    let dotfiles = scanner.scan()?;
+    let state = InstallState::load()?;
+
+    let outputs: Vec<ProfileOutput> = profiles
+        .iter()
+        .map(|profile| {
+            let items = plan_profile(profile, &dotfiles, &state);
+            ProfileOutput {
+                active: is_applied(profile, &items, &state),
+                to_install: items
+                    .iter()
+                    .filter(|item| item.action == ProfileAction::Install)
+                    .map(|item| item.name.clone())
+                    .collect(),
+                unknown: items
+                    .iter()
+                    .filter(|item| item.action == ProfileAction::Unknown)
+                    .map(|item| item.name.clone())
+                    .collect(),
+                profile,
+            }
+        })
+        .collect();
+
+    if format != Format::Table {
+        emit(&ProfileList { profiles: outputs }, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if outputs.is_empty() {
+        println!(
+            "No profiles defined (see [profiles] in {}).",
+            Settings::path().display()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+    for output in &outputs {
+        let profile = output.profile;
+        let state = if output.active {
+            "active".to_string()
+        } else if !output.unknown.is_empty() {
+            format!("unknown entries: {}", output.unknown.join(", "))
+        } else if output.to_install.is_empty() {
+            "up to date".to_string()
+        } else {
+            format!("{} to install", output.to_install.len())
+        };
+        let count = profile.config.entries.len();
+        println!(
+            "{:<20} {:>2} entr{:<3}  {}",
+            profile.name,
+            count,
+            if count == 1 { "y" } else { "ies" },
+            state
+        );
+        if !profile.config.description.is_empty() {
+            println!("   {}", profile.config.description);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Installs the entries of a profile that differ from the current state
+/// and records the profile once all of them are in place.
+fn apply(
+    scanner: &DotfileScanner,
+    name: &str,
+    dry_run: bool,
+    force: bool,
+    keep_going: bool,
+    format: Format,
+) -> Result<ExitCode> {
+    let profiles = profiles(scanner)?;
+    let Some(profile) = find_profile(&profiles, name) else {
+        eprintln!("error: no profile named '{}' (see `dwell profiles`)", name);
+        return Ok(ExitCode::from(EXIT_USAGE));
+    };
+    let dotfiles = scanner.scan()?;
+    let state = InstallState::load()?;
+    let items = plan_profile(profile, &dotfiles, &state);
+
+    let unknown: Vec<&str> = items
+        .iter()
+        .filter(|item| item.action == ProfileAction::Unknown)
+        .map(|item| item.name.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        eprintln!(
+            "error: profile '{}' names unknown entries: {}",
+            profile.name,
+            unknown.join(", ")
+        );
+        return Ok(ExitCode::from(EXIT_USAGE));
+    }
+
+    let up_to_date: Vec<String> = items
+        .iter()
+        .filter(|item| item.action == ProfileAction::UpToDate)
+        .map(|item| item.name.clone())
+        .collect();
+    let pending: Vec<DotfileEntry> = items
+        .into_iter()
+        .filter(|item| item.action == ProfileAction::Install)
+        .filter_map(|item| item.entry)
+        .collect();
+    let entries = match install_order(pending) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+    };
+
+    if format == Format::Table {
+        println!(
+            "Applying profile {}: {} up to date, {} to install",
+            profile.name,
+            up_to_date.len(),
+            entries.len()
+        );
+        for name in &up_to_date {
+            println!("  = {}", name);
+        }
+        println!();
+    }
+
+    if dry_run {
+        emit_plans(&entries, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let results = run_batch(entries, force, keep_going, format);
+    let success = batch_succeeded(&results);
+    if success {
+        record_applied(&profile.name)?;
+    }
+
+    if format != Format::Table {
+        emit(
+            &BatchOutput {
+                profile: Some(profile.name.clone()),
+                up_to_date,
+                success,
+                entries: results,
+            },
+            format,
+        )?;
+    } else if !results.is_empty() {
+        print_batch_summary(&results);
+    }
+
+    if !success {
+        eprintln!("error: profile {} was not fully applied", profile.name);
+        return Ok(ExitCode::from(EXIT_FAILED));
+    }
+    if format == Format::Table {
+        println!("Profile {} is active.", profile.name);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_dependencies(dependencies: &[DepCheck]) {
+    if dependencies.is_empty() {
+        println!("  (none)");
+    }
+    for dep in dependencies {
+        let status = match &dep.status {
+            DepStatus::Installed { path, .. } => format!("✓ {}", path.display()),
+            DepStatus::WrongVersion { found, .. } => {
+                format!("⚠ found {}", found.as_deref().unwrap_or("unknown version"))
+            }
+            DepStatus::Unchecked { path } => {
+                format!("? {} (version checked on install)", path.display())
+            }
+            DepStatus::Missing => "✗ missing".to_string(),
+        };
+        println!("  {:<20} {}", dep.spec, status);
+    }
+}
+
+fn print_plan(plan: &InstallPlan) {
+    println!("Dry run for {} (nothing will be changed)\n", plan.entry);
+
+    println!("Dependencies:");
+    print_dependencies(&plan.dependencies);
+
+    println!("\nBackups:");
+    if plan.backups.is_empty() {
+        println!("  (none)");
+    }
+    for path in &plan.backups {
+        println!("  • {}", path.display());
+    }
+
+    println!("\nFiles:");
+    if plan.steps.is_empty() {
+        println!("  (none)");
+    }
+    for step in &plan.steps {
+        let action = match step.action {
+            DeployAction::Deploy(strategy) => get_deploy_name(strategy),
+            DeployAction::Unchanged => "unchanged",
+            DeployAction::InPlace => "in place",
+            DeployAction::SourceMissing => "missing",
+        };
+        println!(
+            "  {:<10}{} -> {}",
+            action,
+            step.target.file,
+            step.target.dest.display()
+        );
+    }
+
+    println!("\nCommand:");
+    match &plan.command {
+        Some(command) => println!("  $ {}  (in {})", command.display(), command.cwd.display()),
+        None => println!("  (none)"),
+    }
+}
+
+fn print_status(scanner: &DotfileScanner, format: Format) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+    let state = InstallState::load()?;
+
+    let installed: Vec<_> = dotfiles
+        .iter()
+        .filter_map(|entry| {
+            let installed = state.get(entry)?;
+            Some((entry, installed, entry_status(entry, &state)))
+        })
+        .collect();
+
+    if format != Format::Table {
+        let entries = installed
+            .into_iter()
+            .map(|(entry, installed, report)| StatusOutput {
+                name: &entry.config.name,
+                category: &entry.config.category,
+                path: &entry.path,
+                active: state.is_active(entry),
+                status: report.status,
+                installed_at: &installed.installed_at,
+                files: report.files,
+            })
+            .collect();
+        emit(&StatusList { entries }, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if installed.is_empty() {
+        println!("Nothing installed yet.");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!(
+        "{:<24} {:<12} {:<16} {:<16}",
+        "NAME", "CATEGORY", "STATUS", "INSTALLED"
+    );
+    for (entry, installed, report) in installed {
+        let name = if state.is_active(entry) {
+            format!("* {}", entry.config.name)
+        } else {
+            format!("  {}", entry.config.name)
+        };
+        println!(
+            "{:<24} {:<12} {:<16} {:<16}",
+            name,
+            entry.config.category,
+            get_status_name(report.status),
+            installed.installed_at
+        );
+        for file in report.files.iter().filter(|f| f.status != Status::Clean) {
+            println!(
+                "    {:<20} {} ({})",
+                file.file,
+                file.dest.display(),
+                get_status_name(file.status)
+            );
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_diff(scanner: &DotfileScanner, name: &str) -> Result<ExitCode> {
+    let dotfiles = scanner.scan()?;
+    let Some(entry) = find_entry(&dotfiles, name) else {
+        return Ok(unknown_entry(name));
+    };
+
+    for line in diff_entry(entry) {
+        match line {
+            DiffLine::File(text) | DiffLine::Note(text) => println!("{}", text),
+            DiffLine::Hunk(text) => println!("{}", text.trim_end()),
+            DiffLine::Context(text) => println!(" {}", text),
+            DiffLine::Added(text) => println!("+{}", text),
+            DiffLine::Removed(text) => println!("-{}", text),
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn rollback(scanner: &DotfileScanner, entry: Option<&str>, at: Option<&str>) -> Result<ExitCode> {
+    let entry_path = match entry {
+        Some(name) => match find_entry(&scanner.scan()?, name) {
+            Some(entry) => Some(entry.path.clone()),
+            None => return Ok(unknown_entry(name)),
+        },
+        None => None,
+    };
+    let (snapshot, warnings) = find_snapshot(entry_path.as_deref(), at);
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    let Some(snapshot) = snapshot else {
+        eprintln!("error: no matching backup snapshot found");
+        return Ok(ExitCode::from(EXIT_USAGE));
+    };
+
+    println!(
+        "Rolling back {} to {}\n",
+        snapshot.manifest.entry, snapshot.manifest.timestamp
+    );
+
+    let results = restore(&snapshot);
+    for file in &results {
+        match &file.result {
+            Ok(action) => println!("  ✓ {} {}", action, file.path.display()),
+            Err(e) => println!("  ✗ {}: {}", file.path.display(), e),
+        }
+    }
+
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|r| r.result.is_err())
+        .map(|r| r.path.display().to_string())
+        .collect();
+    if !failed.is_empty() {
+        // The install is still partly in place, so its record stays
+        eprintln!(
+            "error: could not restore {}; the snapshot and install record were kept",
+            failed.join(", ")
+        );
+        return Ok(ExitCode::from(EXIT_FAILED));
+    }
+
+    let mut state = InstallState::load()?;
+    state.forget(&snapshot.manifest.path);
+    state.save()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reports files listed in `files` or `targets` that are missing on disk.
+fn validate(scanner: &DotfileScanner, path: Option<&Path>, format: Format) -> Result<ExitCode> {
+    let report = match path {
+        Some(path) if path.is_file() => {
+            let loaded = if path.extension().is_some_and(|ext| ext == "json") {
+                DotfileConfig::from_json(path)
+            } else {
+                DotfileConfig::from_toml(path)
+            };
+            let mut report = ScanReport {
+                entries: vec![],
+                problems: vec![],
+                dirs: vec![],
+            };
+            match loaded {
+                Ok(config) => report.entries.push(DotfileEntry {
+                    config,
+                    path: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+                }),
+                Err(problem) => report.problems.push(problem),
+            }
+            report
+        }
+        Some(path) if path.is_dir() => {
+            DotfileScanner::with_paths(vec![path.to_path_buf()])?.scan_report()?
+        }
+        Some(path) => {
+            eprintln!("error: {} does not exist", path.display());
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+        None => scanner.scan_report()?,
+    };
+    let problems = collect_problems(&report);
+
+    if format != Format::Table {
+        emit(
+            &ValidateOutput {
+                entries: report.entries.len(),
+                problems: &problems,
+            },
+            format,
+        )?;
+    } else if problems.is_empty() {
+        println!("All {} entries are valid.", report.entries.len());
+    } else {
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        println!(
+            "\n{} problem{} found",
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(if problems.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_FAILED)
+    })
+}
@@ -1,14 +1,54 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DotfileConfig {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default = "default_category")]
     pub category: String,
-    pub compiler: Compiler,
+    /// `None` means the package only deploys its files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<Compiler>,
+    /// Programs looked up on `$PATH`, optionally with a version constraint.
+    #[serde(default)]
     pub dependencies: Vec<String>,
+    /// Other entries, by name or directory name, that a batch install puts
+    /// before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Defaults to everything in the package directory except the manifest.
+    #[serde(default)]
     pub files: Vec<String>,
+    /// Destination for each entry in `files`, e.g. `htoprc = "~/.config/htop/htoprc"`.
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+    #[serde(default)]
+    pub deploy: DeployStrategy,
+    /// Theme colors by name, e.g. `color4 = "#81a2be"`. Overrides the colors
+    /// the Preview view would otherwise pick out of `files`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, String>,
+    /// Image shown in the Preview view, relative to the package directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+}
+
+fn default_category() -> String {
+    "misc".to_string()
+}
+
+/// How files with a target are placed on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployStrategy {
+    #[default]
+    Symlink,
+    Copy,
+    Hardlink,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,25 +70,180 @@ pub struct DotfileEntry {
     pub path: PathBuf,
 }
 
+/// A file from `files` resolved against its `targets` destination.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployTarget {
+    pub file: String,
+    pub source: PathBuf,
+    pub dest: PathBuf,
+}
+
+impl DotfileEntry {
+    /// Files that have a destination in `targets`, in `files` order.
+    pub fn deploy_targets(&self) -> Vec<DeployTarget> {
+        self.config
+            .files
+            .iter()
+            .filter_map(|file| {
+                let target = self.config.targets.get(file)?;
+                Some(DeployTarget {
+                    file: file.clone(),
+                    source: self.path.join(file),
+                    dest: crate::paths::expand(target),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Something wrong with a manifest, pointing at the offending line when known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestProblem {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ManifestProblem {
+    pub fn new(file: &Path, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn from_toml_error(file: &Path, content: &str, error: &toml::de::Error) -> Self {
+        let problem = Self::new(file, error.message().trim().replace('\n', ", "));
+        match error.span() {
+            Some(span) => {
+                let (line, column) = line_column(content, span.start);
+                problem.at(line, column)
+            }
+            None => problem,
+        }
+    }
+}
+
+impl fmt::Display for ManifestProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ManifestProblem {}
+
 impl DotfileConfig {
-    pub fn from_toml(path: &PathBuf) -> color_eyre::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: DotfileConfig = toml::from_str(&content)?;
-        Ok(config)
+    pub fn from_toml(path: &Path) -> Result<Self, ManifestProblem> {
+        let content = read_manifest(path)?;
+        let config: Self = toml::from_str(&content)
+            .map_err(|e| ManifestProblem::from_toml_error(path, &content, &e))?;
+        Ok(config.with_default_files(path))
+    }
+
+    pub fn from_json(path: &Path) -> Result<Self, ManifestProblem> {
+        let content = read_manifest(path)?;
+        let config: Self = serde_json::from_str(&content).map_err(|e| {
+            // serde_json appends " at line N column M" to its Display output
+            let message = e.to_string();
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(msg, _)| msg);
+            ManifestProblem::new(path, message).at(e.line(), e.column())
+        })?;
+        Ok(config.with_default_files(path))
+    }
+
+    /// Fills an empty `files` with the manifest's siblings, skipping hidden
+    /// entries and the manifests themselves.
+    fn with_default_files(mut self, manifest: &Path) -> Self {
+        if !self.files.is_empty() {
+            return self;
+        }
+        let Some(dir) = manifest.parent() else {
+            return self;
+        };
+
+        if let Ok(read_dir) = std::fs::read_dir(dir) {
+            self.files = read_dir
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| {
+                    !name.starts_with('.')
+                        && name != "dotwell.toml"
+                        && name != "dotwell.json"
+                        && Some(name) != self.screenshot.as_ref()
+                })
+                .collect();
+            self.files.sort();
+        }
+        self
     }
+}
+
+fn read_manifest(path: &Path) -> Result<String, ManifestProblem> {
+    std::fs::read_to_string(path).map_err(|e| ManifestProblem::new(path, e.to_string()))
+}
 
-    pub fn from_json(path: &PathBuf) -> color_eyre::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: DotfileConfig = serde_json::from_str(&content)?;
-        Ok(config)
+/// 1-based line and column of a byte offset.
+pub fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+pub fn get_deploy_name(strategy: DeployStrategy) -> &'static str {
+    match strategy {
+        DeployStrategy::Symlink => "symlink",
+        DeployStrategy::Copy => "copy",
+        DeployStrategy::Hardlink => "hardlink",
     }
 }
 
-pub fn get_compiler_name(compiler: &Compiler) -> &'static str {
+pub fn get_compiler_name(compiler: &Option<Compiler>) -> &'static str {
     match compiler {
-        Compiler::Gcc { .. } => "gcc",
-        Compiler::Make { .. } => "make",
-        Compiler::Cargo { .. } => "cargo",
-        Compiler::Nix { .. } => "nix",
+        None => "deploy",
+        Some(Compiler::Gcc { .. }) => "gcc",
+        Some(Compiler::Make { .. }) => "make",
+        Some(Compiler::Cargo { .. }) => "cargo",
+        Some(Compiler::Nix { .. }) => "nix",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_column_is_one_based() {
+        assert_eq!(line_column("name = 1", 0), (1, 1));
+        assert_eq!(line_column("name = 1", 7), (1, 8));
+    }
+
+    #[test]
+    fn line_column_counts_lines_and_chars() {
+        let content = "name = \"x\"\ndéps = [\n  1,\n]";
+        assert_eq!(line_column(content, 11), (2, 1));
+        // Columns count characters, not bytes
+        assert_eq!(line_column(content, content.find("= [").unwrap()), (2, 6));
+        assert_eq!(line_column(content, content.rfind('1').unwrap()), (3, 3));
+    }
+
+    #[test]
+    fn line_column_clamps_past_the_end() {
+        assert_eq!(line_column("a\nbc", 100), (2, 3));
     }
 }
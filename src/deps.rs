@@ -0,0 +1,258 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long `<name> --version` may take before the version counts as unknown.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Result of resolving one entry of `dependencies`, e.g. `htop >= 3.0`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepCheck {
+    pub spec: String,
+    #[serde(flatten)]
+    pub status: DepStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DepStatus {
+    Installed {
+        path: PathBuf,
+        version: Option<String>,
+    },
+    /// Found on `$PATH`, but `<name> --version` does not satisfy the constraint.
+    WrongVersion {
+        path: PathBuf,
+        found: Option<String>,
+    },
+    /// Found on `$PATH`; the version constraint was not checked because
+    /// that means running the program.
+    Unchecked {
+        path: PathBuf,
+    },
+    Missing,
+}
+
+impl DepCheck {
+    pub fn is_satisfied(&self) -> bool {
+        matches!(self.status, DepStatus::Installed { .. })
+    }
+}
+
+/// Looks every spec up on `$PATH` and checks version constraints.
+pub fn check_dependencies(specs: &[String]) -> Vec<DepCheck> {
+    specs
+        .iter()
+        .map(|spec| check_dependency(spec, true))
+        .collect()
+}
+
+/// Like `check_dependencies`, but without running anything, for dry runs.
+/// Constraints are reported as `Unchecked`.
+pub fn locate_dependencies(specs: &[String]) -> Vec<DepCheck> {
+    specs
+        .iter()
+        .map(|spec| check_dependency(spec, false))
+        .collect()
+}
+
+fn check_dependency(spec: &str, run_version: bool) -> DepCheck {
+    let (name, constraint) = parse_spec(spec);
+
+    let status = match find_in_path(&name) {
+        None => DepStatus::Missing,
+        Some(path) => match constraint {
+            None => DepStatus::Installed {
+                path,
+                version: None,
+            },
+            Some(_) if !run_version => DepStatus::Unchecked { path },
+            Some((op, wanted)) => {
+                let found = installed_version(&path);
+                let ok = found
+                    .as_deref()
+                    .is_some_and(|found| op.matches(compare_versions(found, &wanted)));
+                if ok {
+                    DepStatus::Installed {
+                        path,
+                        version: found,
+                    }
+                } else {
+                    DepStatus::WrongVersion { path, found }
+                }
+            }
+        },
+    };
+
+    DepCheck {
+        spec: spec.to_string(),
+        status,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// Splits `name [op version]`, accepting `>=`, `>`, `<=`, `<`, `=` and `==`.
+fn parse_spec(spec: &str) -> (String, Option<(Op, String)>) {
+    let Some(pos) = spec.find(['>', '<', '=']) else {
+        return (spec.trim().to_string(), None);
+    };
+
+    let name = spec[..pos].trim().to_string();
+    let rest = &spec[pos..];
+    let (op, version) = [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        ("==", Op::Eq),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+        ("=", Op::Eq),
+    ]
+    .into_iter()
+    .find_map(|(token, op)| Some((op, rest.strip_prefix(token)?)))
+    .unwrap_or((Op::Ge, rest));
+
+    (name, Some((op, version.trim().to_string())))
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| {
+            candidate
+                .metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+}
+
+/// Runs `<binary> --version` and pulls the first version-looking token out
+/// of it. A program still running after `VERSION_TIMEOUT` is killed.
+fn installed_version(binary: &PathBuf) -> Option<String> {
+    let child = Command::new(binary)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .ok()?;
+    let pid = child.id();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+    let output = match rx.recv_timeout(VERSION_TIMEOUT) {
+        Ok(output) => output.ok()?,
+        Err(_) => {
+            // SAFETY: killpg only sends a signal; the group id is the child's pid
+            unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+            return None;
+        }
+    };
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    text.split_whitespace().find_map(|word| {
+        let word = word.trim_start_matches('v');
+        let version: String = word
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let version = version.trim_end_matches('.');
+        (version.contains('.') && version.starts_with(|c: char| c.is_ascii_digit()))
+            .then(|| version.to_string())
+    })
+}
+
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    let (a, b) = (parse(a), parse(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(spec: &str) -> Option<(Op, String)> {
+        parse_spec(spec).1
+    }
+
+    #[test]
+    fn parse_spec_without_operator_has_no_constraint() {
+        assert_eq!(parse_spec("htop"), ("htop".to_string(), None));
+        assert_eq!(parse_spec("  htop "), ("htop".to_string(), None));
+    }
+
+    #[test]
+    fn parse_spec_reads_operators() {
+        assert_eq!(parse_spec("htop >= 3.0").0, "htop");
+        assert_eq!(constraint("htop >= 3.0"), Some((Op::Ge, "3.0".to_string())));
+        assert_eq!(constraint("htop>=3.0"), Some((Op::Ge, "3.0".to_string())));
+        assert_eq!(
+            constraint("htop == 3.2.1"),
+            Some((Op::Eq, "3.2.1".to_string()))
+        );
+        assert_eq!(constraint("htop = 3"), Some((Op::Eq, "3".to_string())));
+        assert_eq!(constraint("htop <= 4"), Some((Op::Le, "4".to_string())));
+        assert_eq!(constraint("htop > 2"), Some((Op::Gt, "2".to_string())));
+        assert_eq!(constraint("htop < 5"), Some((Op::Lt, "5".to_string())));
+    }
+
+    #[test]
+    fn compare_versions_pads_missing_parts() {
+        assert_eq!(compare_versions("3.0", "3.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("3.0.1", "3.0"), Ordering::Greater);
+        assert_eq!(compare_versions("3.0", "3.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn compare_versions_is_numeric() {
+        assert_eq!(compare_versions("3.10", "3.9"), Ordering::Greater);
+        assert_eq!(compare_versions("10.0", "9.99"), Ordering::Greater);
+    }
+
+    #[test]
+    fn operators_match_orderings() {
+        assert!(Op::Ge.matches(compare_versions("3.0", "3.0")));
+        assert!(!Op::Ge.matches(compare_versions("3.0", "3.0.1")));
+        assert!(Op::Lt.matches(compare_versions("3.0", "3.0.1")));
+        assert!(Op::Eq.matches(compare_versions("3.0.0", "3")));
+        assert!(!Op::Gt.matches(compare_versions("3.0", "3.0.0")));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::DotfileEntry;
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One rendered line of a unified diff.
+#[derive(Debug, Clone)]
+pub enum DiffLine {
+    /// `--- deployed` / `+++ source` style file header.
+    File(String),
+    Hunk(String),
+    Context(String),
+    Added(String),
+    Removed(String),
+    Note(String),
+}
+
+/// Unified diff between each deployed target and the file that would replace it.
+pub fn diff_entry(entry: &DotfileEntry) -> Vec<DiffLine> {
+    let targets = entry.deploy_targets();
+    if targets.is_empty() {
+        return vec![DiffLine::Note(
+            "No files with a target to compare.".to_string(),
+        )];
+    }
+
+    let mut lines = vec![];
+    for target in targets {
+        if !target.source.exists() {
+            lines.push(DiffLine::File(format!("+++ {}", target.source.display())));
+            lines.push(DiffLine::Note("source file is missing".to_string()));
+            continue;
+        }
+        for (source, dest) in file_pairs(&target.source, &target.dest) {
+            diff_file(&source, &dest, &mut lines);
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(DiffLine::Note("No differences.".to_string()));
+    }
+    lines
+}
+
+/// Pairs every file below `source` or `dest` with its counterpart on the
+/// other side, which may not exist. Files only in the deployed directory
+/// are the ones an install would remove.
+fn file_pairs(source: &Path, dest: &Path) -> Vec<(PathBuf, PathBuf)> {
+    if !source.is_dir() && !dest.is_dir() {
+        return vec![(source.to_path_buf(), dest.to_path_buf())];
+    }
+
+    let mut names: Vec<_> = [source, dest]
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|read_dir| read_dir.flatten().map(|child| child.file_name()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+        .iter()
+        .flat_map(|name| file_pairs(&source.join(name), &dest.join(name)))
+        .collect()
+}
+
+fn diff_file(source: &Path, dest: &Path, lines: &mut Vec<DiffLine>) {
+    let new = fs::read(source).unwrap_or_default();
+    let old = fs::read(dest).unwrap_or_default();
+    if old == new && source.exists() == dest.exists() {
+        return;
+    }
+
+    let label = |path: &Path| {
+        if path.exists() {
+            path.display().to_string()
+        } else {
+            "/dev/null".to_string()
+        }
+    };
+    lines.push(DiffLine::File(format!("--- {}", label(dest))));
+    lines.push(DiffLine::File(format!("+++ {}", label(source))));
+
+    let (Ok(old), Ok(new)) = (String::from_utf8(old), String::from_utf8(new)) else {
+        lines.push(DiffLine::Note("binary files differ".to_string()));
+        return;
+    };
+
+    let diff = TextDiff::from_lines(&old, &new);
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        lines.push(DiffLine::Hunk(hunk.header().to_string()));
+        for change in hunk.iter_changes() {
+            let text = change.value().trim_end_matches('\n').to_string();
+            lines.push(match change.tag() {
+                ChangeTag::Equal => DiffLine::Context(text),
+                ChangeTag::Insert => DiffLine::Added(text),
+                ChangeTag::Delete => DiffLine::Removed(text),
+            });
+        }
+    }
+}
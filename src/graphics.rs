@@ -0,0 +1,287 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::{cursor::MoveTo, QueueableCommand};
+use image::imageops::FilterType;
+use image::RgbaImage;
+use ratatui::layout::Rect;
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Screenshots are scaled down to this once, on load.
+const MAX_SOURCE_SIDE: u32 = 1024;
+/// Kitty accepts at most this much base64 per escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+/// How images are drawn in the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// Pick from the environment.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    /// Two pixels per cell with `▀`, works in any true-color terminal.
+    Halfblocks,
+}
+
+impl Protocol {
+    /// Resolves `Auto` by looking at the terminal's environment variables.
+    pub fn resolve(self) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+
+        let var = |name| std::env::var(name).unwrap_or_default().to_lowercase();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        if std::env::var_os("TMUX").is_some() {
+            // Escape sequences would need passthrough wrapping
+            Self::Halfblocks
+        } else if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "wezterm"
+            || program == "ghostty"
+        {
+            Self::Kitty
+        } else if term.starts_with("foot")
+            || term.contains("mlterm")
+            || term.contains("sixel")
+            || program == "iterm.app"
+        {
+            Self::Sixel
+        } else {
+            Self::Halfblocks
+        }
+    }
+}
+
+/// A decoded screenshot, ready to be scaled into any area.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    /// Changes whenever a different image is loaded.
+    pub id: u32,
+    pub image: RgbaImage,
+}
+
+impl Screenshot {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+        let image = image::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let image = if image.width().max(image.height()) > MAX_SOURCE_SIDE {
+            image.resize(MAX_SOURCE_SIDE, MAX_SOURCE_SIDE, FilterType::Triangle)
+        } else {
+            image
+        };
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            image: image.into_rgba8(),
+        })
+    }
+
+    /// The largest area inside `area` that keeps the image's aspect ratio,
+    /// centered horizontally.
+    pub fn fit(&self, area: Rect) -> Rect {
+        let (cell_width, cell_height) = cell_size();
+        let image_ratio = self.image.width() as f32 / self.image.height().max(1) as f32;
+        let area_ratio = (area.width as f32 * cell_width) / (area.height as f32 * cell_height);
+
+        let (width, height) = if image_ratio > area_ratio {
+            let height = area.width as f32 * cell_width / image_ratio / cell_height;
+            (area.width, (height.round() as u16).clamp(1, area.height))
+        } else {
+            let width = area.height as f32 * cell_height * image_ratio / cell_width;
+            ((width.round() as u16).clamp(1, area.width), area.height)
+        };
+        Rect::new(area.x + (area.width - width) / 2, area.y, width, height)
+    }
+
+    /// Top and bottom pixel colors of each cell, `rows` × `columns`.
+    pub fn half_blocks(&self, columns: u16, rows: u16) -> Vec<Vec<[(u8, u8, u8); 2]>> {
+        let scaled = image::imageops::thumbnail(&self.image, columns as u32, rows as u32 * 2);
+        let pixel = |x, y| {
+            let [r, g, b, a] = scaled.get_pixel(x, y).0;
+            // Transparent parts blend into a black background
+            let blend = |c: u8| (c as u16 * a as u16 / 255) as u8;
+            (blend(r), blend(g), blend(b))
+        };
+        (0..scaled.height() / 2)
+            .map(|row| {
+                (0..scaled.width())
+                    .map(|x| [pixel(x, row * 2), pixel(x, row * 2 + 1)])
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Pixel size of a terminal cell, assuming the usual 1:2 when the terminal
+/// does not report it.
+fn cell_size() -> (f32, f32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            size.width as f32 / size.columns as f32,
+            size.height as f32 / size.rows as f32,
+        ),
+        _ => (10.0, 20.0),
+    }
+}
+
+/// Draws screenshots with the kitty or sixel protocol over the cells the
+/// UI left empty for them, skipping redraws of an unchanged image.
+pub struct ImageOutput {
+    protocol: Protocol,
+    shown: Option<(u32, Rect)>,
+}
+
+impl ImageOutput {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            shown: None,
+        }
+    }
+
+    /// Shows `image` in `area`, or removes the current image for `None`.
+    /// Returns true when the screen needs a full redraw to get rid of an
+    /// old sixel image.
+    pub fn show(
+        &mut self,
+        out: &mut impl Write,
+        image: Option<(&Screenshot, Rect)>,
+    ) -> io::Result<bool> {
+        let wanted = image.map(|(screenshot, area)| (screenshot.id, area));
+        if wanted == self.shown {
+            return Ok(false);
+        }
+
+        let had_image = self.shown.take().is_some();
+        if had_image && self.protocol == Protocol::Kitty {
+            out.write_all(b"\x1b_Ga=d,q=2\x1b\\")?;
+        }
+        let Some((screenshot, area)) = image else {
+            out.flush()?;
+            return Ok(had_image && self.protocol == Protocol::Sixel);
+        };
+
+        let area = screenshot.fit(area);
+        out.queue(MoveTo(area.x, area.y))?;
+        match self.protocol {
+            Protocol::Kitty => write_kitty(out, &screenshot.image, area)?,
+            Protocol::Sixel => {
+                let (cell_width, cell_height) = cell_size();
+                let width = (area.width as f32 * cell_width) as u32;
+                let height = (area.height as f32 * cell_height) as u32;
+                let scaled = image::imageops::resize(
+                    &screenshot.image,
+                    width.max(1),
+                    height.max(1),
+                    FilterType::Triangle,
+                );
+                write_sixel(out, &scaled)?;
+            }
+            Protocol::Auto | Protocol::Halfblocks => {}
+        }
+        out.flush()?;
+        self.shown = wanted;
+        Ok(false)
+    }
+}
+
+/// Transmits raw RGBA and lets the terminal scale it into `area`.
+fn write_kitty(out: &mut impl Write, image: &RgbaImage, area: Rect) -> io::Result<()> {
+    let data = STANDARD.encode(image.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            write!(
+                out,
+                "\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={};",
+                image.width(),
+                image.height(),
+                area.width,
+                area.height,
+                more
+            )?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+        out.write_all(chunk)?;
+        out.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// Encodes `image` as sixels using a 6×6×6 color cube.
+fn write_sixel(out: &mut impl Write, image: &RgbaImage) -> io::Result<()> {
+    const LEVELS: u16 = 6;
+    let index = |x: u32, y: u32| -> usize {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let level = |c: u8| ((c as u16 * a as u16 / 255 * (LEVELS - 1) + 127) / 255) as usize;
+        level(r) * 36 + level(g) * 6 + level(b)
+    };
+
+    write!(out, "\x1bPq\"1;1;{};{}", image.width(), image.height())?;
+    for i in 0..216 {
+        let percent = |level: usize| level * 100 / (LEVELS as usize - 1);
+        write!(out, "#{};2;{};{};{}", i, percent(i / 36), percent(i / 6 % 6), percent(i % 6))?;
+    }
+
+    for band in (0..image.height()).step_by(6) {
+        let rows = (image.height() - band).min(6);
+        // Color of every pixel in the band, column by column
+        let pixels: Vec<usize> = (0..image.width())
+            .flat_map(|x| (0..rows).map(move |dy| (x, band + dy)))
+            .map(|(x, y)| index(x, y))
+            .collect();
+        let mut colors = pixels.clone();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, color) in colors.iter().enumerate() {
+            if n > 0 {
+                out.write_all(b"$")?;
+            }
+            write!(out, "#{}", color)?;
+            let sixels = pixels.chunks(rows as usize).map(|column| {
+                let bits = column
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, pixel)| *pixel == color)
+                    .fold(0u8, |bits, (dy, _)| bits | 1 << dy);
+                (63 + bits) as char
+            });
+            write_run_length(out, sixels)?;
+        }
+        out.write_all(b"-")?;
+    }
+    out.write_all(b"\x1b\\")
+}
+
+/// Writes sixel characters, collapsing repeats into `!count char`.
+fn write_run_length(out: &mut impl Write, sixels: impl Iterator<Item = char>) -> io::Result<()> {
+    let mut run: Option<(char, usize)> = None;
+    let flush = |out: &mut dyn Write, (c, count): (char, usize)| match count {
+        1..=3 => write!(out, "{}", c.to_string().repeat(count)),
+        _ => write!(out, "!{}{}", count, c),
+    };
+    for c in sixels {
+        match &mut run {
+            Some((last, count)) if *last == c => *count += 1,
+            _ => {
+                if let Some(done) = run.take() {
+                    flush(out, done)?;
+                }
+                run = Some((c, 1));
+            }
+        }
+    }
+    if let Some(done) = run {
+        flush(out, done)?;
+    }
+    Ok(())
+}
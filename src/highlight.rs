@@ -0,0 +1,528 @@
+use std::fs;
+use std::path::Path;
+
+/// Files larger than this are cut off in the preview.
+const MAX_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// Syntax used to colour a previewed file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    /// `key = value` and `key value` files: ini, htoprc, kitty.conf, Xresources.
+    Ini,
+    Toml,
+    Lua,
+    Shell,
+    Nix,
+    Css,
+    Json,
+    Plain,
+}
+
+impl Language {
+    /// Picks a language from the file name, falling back to the shebang.
+    pub fn detect(path: &Path, first_line: &str) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "toml" => return Self::Toml,
+            "lua" => return Self::Lua,
+            "sh" | "bash" | "zsh" | "fish" => return Self::Shell,
+            "nix" => return Self::Nix,
+            "css" | "scss" | "rasi" => return Self::Css,
+            "json" | "jsonc" => return Self::Json,
+            "ini" | "conf" | "cfg" | "desktop" | "theme" => return Self::Ini,
+            _ => {}
+        }
+
+        if let Some(interpreter) = first_line.strip_prefix("#!") {
+            if ["sh", "bash", "zsh", "fish", "dash"]
+                .iter()
+                .any(|shell| interpreter.trim_end().ends_with(shell))
+            {
+                return Self::Shell;
+            }
+        }
+
+        match name.as_str() {
+            ".bashrc" | ".zshrc" | ".profile" | ".bash_profile" | ".zprofile" | "bashrc"
+            | "zshrc" => Self::Shell,
+            ".xresources" | "xresources" | ".xdefaults" | "config" => Self::Ini,
+            _ if name.ends_with("rc") => Self::Ini,
+            _ => Self::Plain,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ini => "ini",
+            Self::Toml => "toml",
+            Self::Lua => "lua",
+            Self::Shell => "shell",
+            Self::Nix => "nix",
+            Self::Css => "css",
+            Self::Json => "json",
+            Self::Plain => "text",
+        }
+    }
+
+    fn line_comment(self) -> &'static [&'static str] {
+        match self {
+            Self::Ini => &["#", ";", "!"],
+            Self::Toml | Self::Shell | Self::Nix => &["#"],
+            Self::Lua => &["--"],
+            Self::Css | Self::Json => &["//"],
+            Self::Plain => &[],
+        }
+    }
+
+    /// Opening and closing delimiters of block comments.
+    fn block_comment(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::Css | Self::Nix | Self::Json => Some(("/*", "*/")),
+            Self::Lua => Some(("--[[", "]]")),
+            _ => None,
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::Lua => &[
+                "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if",
+                "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until",
+                "while",
+            ],
+            Self::Shell => &[
+                "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case",
+                "esac", "in", "function", "return", "export", "local", "alias", "source", "set",
+                "unset",
+            ],
+            Self::Nix => &[
+                "let", "in", "with", "rec", "inherit", "if", "then", "else", "assert", "import",
+                "true", "false", "null",
+            ],
+            Self::Toml | Self::Json | Self::Ini => &["true", "false", "null", "yes", "no"],
+            Self::Css => &["!important"],
+            Self::Plain => &[],
+        }
+    }
+}
+
+/// What a run of characters is, for colouring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Text,
+    Comment,
+    String,
+    Number,
+    Keyword,
+    /// Assigned name: ini/toml keys, JSON object keys, CSS properties.
+    Key,
+    /// `[section]` headers and CSS selectors.
+    Section,
+    Punctuation,
+}
+
+pub type HighlightedLine = Vec<(TokenKind, String)>;
+
+/// A file loaded for the preview pane.
+#[derive(Debug, Clone)]
+pub struct FileView {
+    pub language: Language,
+    pub lines: Vec<HighlightedLine>,
+    /// Why there is nothing to show, e.g. a binary file.
+    pub note: Option<String>,
+}
+
+impl FileView {
+    fn note(message: impl Into<String>) -> Self {
+        Self {
+            language: Language::Plain,
+            lines: vec![],
+            note: Some(message.into()),
+        }
+    }
+}
+
+/// Reads and highlights `path`. Directories list their contents instead.
+pub fn load_file(path: &Path) -> FileView {
+    if path.is_dir() {
+        let mut names: Vec<String> = match fs::read_dir(path) {
+            Ok(read_dir) => read_dir
+                .flatten()
+                .map(|child| {
+                    let name = child.file_name().to_string_lossy().into_owned();
+                    if child.path().is_dir() {
+                        format!("{}/", name)
+                    } else {
+                        name
+                    }
+                })
+                .collect(),
+            Err(e) => return FileView::note(e.to_string()),
+        };
+        names.sort();
+        return FileView {
+            language: Language::Plain,
+            lines: names
+                .into_iter()
+                .map(|name| vec![(TokenKind::Text, name)])
+                .collect(),
+            note: None,
+        };
+    }
+
+    let mut bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return FileView::note(e.to_string()),
+    };
+    if bytes.contains(&0) {
+        return FileView::note("binary file");
+    }
+    let truncated = bytes.len() > MAX_PREVIEW_BYTES;
+    bytes.truncate(MAX_PREVIEW_BYTES);
+    let content = String::from_utf8_lossy(&bytes);
+
+    let language = Language::detect(path, content.lines().next().unwrap_or(""));
+    FileView {
+        language,
+        lines: highlight(language, &content),
+        note: truncated.then(|| format!("truncated to {} KiB", MAX_PREVIEW_BYTES / 1024)),
+    }
+}
+
+/// Splits `content` into coloured tokens, line by line.
+pub fn highlight(language: Language, content: &str) -> Vec<HighlightedLine> {
+    let mut in_block_comment = false;
+    content
+        .lines()
+        .map(|line| highlight_line(language, line, &mut in_block_comment))
+        .collect()
+}
+
+fn highlight_line(language: Language, line: &str, in_block_comment: &mut bool) -> HighlightedLine {
+    let mut tokens = Tokens::default();
+    let mut rest = line;
+
+    if *in_block_comment {
+        let (_, close) = language.block_comment().unwrap_or(("", ""));
+        match rest.find(close) {
+            Some(end) => {
+                let end = end + close.len();
+                tokens.push(TokenKind::Comment, &rest[..end]);
+                rest = &rest[end..];
+                *in_block_comment = false;
+            }
+            None => {
+                tokens.push(TokenKind::Comment, rest);
+                return tokens.0;
+            }
+        }
+    }
+
+    let trimmed = rest.trim_start();
+    let indent = &rest[..rest.len() - trimmed.len()];
+    if language
+        .line_comment()
+        .iter()
+        .any(|c| trimmed.starts_with(c))
+        && !language
+            .block_comment()
+            .is_some_and(|(open, _)| trimmed.starts_with(open))
+    {
+        tokens.push(TokenKind::Text, indent);
+        tokens.push(TokenKind::Comment, trimmed);
+        return tokens.0;
+    }
+
+    if matches!(language, Language::Ini | Language::Toml) {
+        if trimmed.starts_with('[') {
+            tokens.push(TokenKind::Text, indent);
+            tokens.push(TokenKind::Section, trimmed);
+            return tokens.0;
+        }
+        if let Some(key_len) = ini_key_len(language, trimmed) {
+            tokens.push(TokenKind::Text, indent);
+            tokens.push(TokenKind::Key, &trimmed[..key_len]);
+            rest = &trimmed[key_len..];
+        }
+    }
+
+    scan(language, rest, &mut tokens, in_block_comment);
+    tokens.0
+}
+
+/// Length of the key at the start of an ini-style line, if there is one.
+fn ini_key_len(language: Language, line: &str) -> Option<usize> {
+    let end = match language {
+        // TOML keys always come before an `=`
+        Language::Toml => line.find('=')?,
+        _ => line.find(|c: char| c == '=' || c == ':' || c.is_whitespace())?,
+    };
+    let key = line[..end].trim_end();
+    (!key.is_empty() && !key.starts_with(['"', '\''])).then_some(key.len())
+}
+
+/// Tokenizes the rest of a line: strings, comments, numbers and words.
+fn scan(language: Language, line: &str, tokens: &mut Tokens, in_block_comment: &mut bool) {
+    let mut chars = line.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let rest = &line[start..];
+
+        if let Some((open, close)) = language.block_comment() {
+            if let Some(body) = rest.strip_prefix(open) {
+                match body.find(close) {
+                    Some(end) => {
+                        let end = open.len() + end + close.len();
+                        tokens.push(TokenKind::Comment, &rest[..end]);
+                        advance(&mut chars, start + end);
+                    }
+                    None => {
+                        tokens.push(TokenKind::Comment, rest);
+                        *in_block_comment = true;
+                        return;
+                    }
+                }
+                continue;
+            }
+        }
+
+        if starts_comment(
+            language,
+            rest,
+            start == 0 || line[..start].ends_with(char::is_whitespace),
+        ) {
+            tokens.push(TokenKind::Comment, rest);
+            return;
+        }
+
+        if c == '"' || (c == '\'' && language != Language::Json) {
+            let end = string_end(rest, c);
+            let text = &rest[..end];
+            let kind = if language == Language::Json && rest[end..].trim_start().starts_with(':') {
+                TokenKind::Key
+            } else {
+                TokenKind::String
+            };
+            tokens.push(kind, text);
+            advance(&mut chars, start + end);
+            continue;
+        }
+
+        if (c == '#' && (language == Language::Css || is_hex_color(rest))) || c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '#' || c == '%'))
+                .unwrap_or(rest.len());
+            tokens.push(TokenKind::Number, &rest[..end]);
+            advance(&mut chars, start + end);
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' || c == '-' || c == '$' || c == '!' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$' | '!')))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            tokens.push(word_kind(language, word, &rest[end..]), word);
+            advance(&mut chars, start + end);
+            continue;
+        }
+
+        let kind = if c.is_whitespace() || c.is_alphanumeric() {
+            TokenKind::Text
+        } else {
+            TokenKind::Punctuation
+        };
+        tokens.push(kind, &rest[..c.len_utf8()]);
+        chars.next();
+    }
+}
+
+/// Whether a line comment starts here. `#` only counts at a word boundary
+/// so colours like `#1e1e2e` and `$#` stay intact in shell and ini files.
+fn starts_comment(language: Language, rest: &str, at_boundary: bool) -> bool {
+    language.line_comment().iter().any(|marker| {
+        rest.starts_with(marker)
+            && (*marker != "#" || at_boundary)
+            && !(language == Language::Ini && *marker == "#" && is_hex_color(rest))
+    })
+}
+
+fn is_hex_color(text: &str) -> bool {
+    let digits = text[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit())
+        .count();
+    matches!(digits, 3 | 6 | 8) && !text[1 + digits..].starts_with(|c: char| c.is_alphanumeric())
+}
+
+fn word_kind(language: Language, word: &str, after: &str) -> TokenKind {
+    if language.keywords().contains(&word) {
+        return TokenKind::Keyword;
+    }
+    let after = after.trim_start();
+    match language {
+        Language::Css if after.starts_with(':') && !after.starts_with("::") => TokenKind::Key,
+        Language::Css if after.starts_with('{') || after.starts_with(',') => TokenKind::Section,
+        Language::Nix | Language::Lua if after.starts_with('=') && !after.starts_with("==") => {
+            TokenKind::Key
+        }
+        Language::Shell if word.starts_with('$') => TokenKind::Key,
+        _ => TokenKind::Text,
+    }
+}
+
+/// Byte offset just past the closing `quote`, or the end of the line.
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return i + c.len_utf8(),
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
+
+fn advance(chars: &mut std::iter::Peekable<std::str::CharIndices>, to: usize) {
+    while chars.peek().is_some_and(|&(i, _)| i < to) {
+        chars.next();
+    }
+}
+
+/// Tokens of one line, merging neighbours of the same kind.
+#[derive(Default)]
+struct Tokens(HighlightedLine);
+
+impl Tokens {
+    fn push(&mut self, kind: TokenKind, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.0.last_mut() {
+            Some((last, existing)) if *last == kind => existing.push_str(text),
+            _ => self.0.push((kind, text.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    fn tokens(line: &HighlightedLine) -> Vec<(TokenKind, &str)> {
+        line.iter()
+            .map(|(kind, text)| (*kind, text.as_str()))
+            .collect()
+    }
+
+    fn highlight_one(language: Language, line: &str) -> HighlightedLine {
+        highlight_line(language, line, &mut false)
+    }
+
+    #[test]
+    fn toml_key_string_and_trailing_comment() {
+        let line = highlight_one(Language::Toml, "name = \"foot\" # theme");
+        assert_eq!(
+            tokens(&line),
+            vec![
+                (Key, "name"),
+                (Text, " "),
+                (Punctuation, "="),
+                (Text, " "),
+                (String, "\"foot\""),
+                (Text, " "),
+                (Comment, "# theme"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ini_sections_comments_and_colors() {
+        let section = highlight_one(Language::Ini, "[colors]");
+        assert_eq!(tokens(&section), vec![(Section, "[colors]")]);
+
+        let comment = highlight_one(Language::Ini, "  ; font_size 11");
+        assert_eq!(
+            tokens(&comment),
+            vec![(Text, "  "), (Comment, "; font_size 11")]
+        );
+
+        // A hex color is a value, not a `#` comment
+        let color = highlight_one(Language::Ini, "background #1e1e2e");
+        assert_eq!(
+            tokens(&color),
+            vec![(Key, "background"), (Text, " "), (Number, "#1e1e2e")]
+        );
+    }
+
+    #[test]
+    fn shell_keywords_and_variables() {
+        let line = highlight_one(Language::Shell, "export PATH=$HOME/bin");
+        assert_eq!(
+            tokens(&line),
+            vec![
+                (Keyword, "export"),
+                (Text, " PATH"),
+                (Punctuation, "="),
+                (Key, "$HOME"),
+                (Punctuation, "/"),
+                (Text, "bin"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lua_assignment_and_comment() {
+        let line = highlight_one(Language::Lua, "x = 1 -- note");
+        assert_eq!(
+            tokens(&line),
+            vec![
+                (Key, "x"),
+                (Text, " "),
+                (Punctuation, "="),
+                (Text, " "),
+                (Number, "1"),
+                (Text, " "),
+                (Comment, "-- note"),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comment_spans_lines() {
+        let lines = highlight(Language::Css, "a { /* start\nstill */ color: red; }");
+        assert_eq!(
+            tokens(&lines[0]),
+            vec![
+                (Section, "a"),
+                (Text, " "),
+                (Punctuation, "{"),
+                (Text, " "),
+                (Comment, "/* start"),
+            ]
+        );
+        assert_eq!(
+            tokens(&lines[1]),
+            vec![
+                (Comment, "still */"),
+                (Text, " "),
+                (Key, "color"),
+                (Punctuation, ":"),
+                (Text, " red"),
+                (Punctuation, ";"),
+                (Text, " "),
+                (Punctuation, "}"),
+            ]
+        );
+    }
+}
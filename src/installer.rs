@@ -1,66 +1,610 @@
-use crate::config::{Compiler, DotfileEntry};
+use crate::backup;
+use crate::config::{Compiler, DeployStrategy, DeployTarget, DotfileEntry};
+use crate::deps::{check_dependencies, locate_dependencies, DepCheck};
+use crate::state::InstallState;
+use color_eyre::eyre::WrapErr;
 use color_eyre::Result;
-use std::process::Command;
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-pub struct InstallResult {
-    pub success: bool,
-    pub output: String,
+/// Everything an install would do, worked out without touching the
+/// filesystem or spawning anything. Dry runs stop here.
+#[derive(Serialize)]
+pub struct InstallPlan {
+    pub entry: String,
+    pub dependencies: Vec<DepCheck>,
+    pub steps: Vec<DeployStep>,
+    /// Existing files that would be snapshotted before being replaced.
+    pub backups: Vec<PathBuf>,
+    pub command: Option<PlannedCommand>,
 }
 
-pub fn install_dotfile(entry: &DotfileEntry) -> Result<InstallResult> {
-    let output = match &entry.config.compiler {
-        Compiler::Gcc { flags } => {
-            let mut cmd = Command::new("gcc");
-            if let Some(flags) = flags {
-                cmd.args(flags);
+#[derive(Serialize)]
+pub struct DeployStep {
+    pub target: DeployTarget,
+    pub action: DeployAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployAction {
+    Deploy(DeployStrategy),
+    /// Already a symlink to the source.
+    Unchanged,
+    /// The target is the source file itself, e.g. a package kept in the
+    /// directory it deploys to.
+    InPlace,
+    SourceMissing,
+}
+
+#[derive(Serialize)]
+pub struct PlannedCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+}
+
+impl PlannedCommand {
+    pub fn display(&self) -> String {
+        let mut parts = vec![self.program.clone()];
+        parts.extend(self.args.iter().cloned());
+        parts.join(" ")
+    }
+
+    fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args).current_dir(&self.cwd);
+        cmd
+    }
+}
+
+pub fn plan_install(entry: &DotfileEntry) -> InstallPlan {
+    let strategy = entry.config.deploy;
+
+    let steps: Vec<DeployStep> = entry
+        .deploy_targets()
+        .into_iter()
+        .map(|target| {
+            let action = if !target.source.exists() {
+                DeployAction::SourceMissing
+            } else if strategy == DeployStrategy::Symlink
+                && is_linked_to(&target.dest, &target.source)
+            {
+                DeployAction::Unchanged
+            } else if is_source_itself(&target.dest, &target.source) {
+                DeployAction::InPlace
+            } else {
+                DeployAction::Deploy(strategy)
+            };
+            DeployStep { target, action }
+        })
+        .collect();
+
+    let backups = steps
+        .iter()
+        .filter(|step| matches!(step.action, DeployAction::Deploy(_)))
+        .filter(|step| fs::symlink_metadata(&step.target.dest).is_ok())
+        .filter(|step| !backup::matches_source(&step.target))
+        .map(|step| step.target.dest.clone())
+        .collect();
+
+    InstallPlan {
+        entry: entry.config.name.clone(),
+        // A plan runs nothing, not even `--version`
+        dependencies: locate_dependencies(&entry.config.dependencies),
+        steps,
+        backups,
+        command: compiler_command(entry),
+    }
+}
+
+/// Progress reported by an install running in the background.
+pub enum InstallEvent {
+    Line(String),
+    /// A batch install moved an entry, by path, to a new stage.
+    Entry(PathBuf, EntryOutcome),
+    /// `Ok(success)` once the install is over, `Err` if it could not run.
+    Finished(Result<bool, String>),
+}
+
+/// Where one entry of a batch install stands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", content = "reason", rename_all = "snake_case")]
+pub enum EntryOutcome {
+    Pending,
+    Running,
+    Installed,
+    Failed,
+    /// Not attempted, with the reason.
+    Skipped(String),
+}
+
+/// A running install. Dropping it does not stop the install.
+pub struct InstallHandle {
+    pub events: Receiver<InstallEvent>,
+    cancel: Arc<Cancel>,
+}
+
+impl InstallHandle {
+    /// Kills the compiler process and everything it started, if one is
+    /// running, before returning. Entries not started yet are skipped.
+    pub fn cancel(&self) {
+        self.cancel.request();
+    }
+}
+
+/// Cancellation shared between an install thread and its handle.
+#[derive(Default)]
+struct Cancel {
+    requested: AtomicBool,
+    /// Process group of the running command, 0 while none runs.
+    group: AtomicI32,
+}
+
+impl Cancel {
+    fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.kill_group();
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    fn kill_group(&self) {
+        let group = self.group.load(Ordering::SeqCst);
+        if group > 0 {
+            // SAFETY: killpg only sends a signal to the command's own group
+            unsafe { libc::killpg(group, libc::SIGKILL) };
+        }
+    }
+}
+
+/// Installs `entry` on a background thread, streaming every log line and
+/// compiler output line over the handle's channel as it is produced.
+/// Missing dependencies abort the install unless `force` is set.
+pub fn spawn_install(entry: DotfileEntry, force: bool) -> InstallHandle {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(Cancel::default());
+
+    let flag = Arc::clone(&cancel);
+    thread::spawn(move || {
+        let result = run_install(&entry, force, &tx, &flag).map_err(|e| format!("{:#}", e));
+        let _ = tx.send(InstallEvent::Finished(result));
+    });
+
+    InstallHandle { events: rx, cancel }
+}
+
+/// Orders `entries` so that each comes after the entries it depends on.
+/// An entry depends on another when its `requires` names that entry.
+/// Otherwise the given order is kept. Fails on a dependency cycle.
+pub fn install_order(entries: Vec<DotfileEntry>) -> Result<Vec<DotfileEntry>, String> {
+    let mut remaining = entries;
+    let mut ordered: Vec<DotfileEntry> = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|entry| {
+            remaining
+                .iter()
+                .all(|other| other.path == entry.path || !depends_on(entry, other))
+        });
+        match ready {
+            Some(index) => ordered.push(remaining.remove(index)),
+            None => {
+                let names: Vec<&str> = remaining.iter().map(|e| e.config.name.as_str()).collect();
+                return Err(format!("dependency cycle between {}", names.join(", ")));
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+/// Whether `entry` requires `other`, by name or directory.
+fn depends_on(entry: &DotfileEntry, other: &DotfileEntry) -> bool {
+    let dir = other.path.file_name().map(|name| name.to_string_lossy());
+    entry.config.requires.iter().any(|name| {
+        let name = name.trim();
+        name.eq_ignore_ascii_case(&other.config.name) || dir.as_deref() == Some(name)
+    })
+}
+
+/// Installs `entries` one after another in the given order, reporting each
+/// entry's progress with `InstallEvent::Entry`. After a failure the rest is
+/// skipped unless `keep_going` is set; entries depending on a failed one
+/// are skipped either way. Finishes with `Ok(true)` only if all installed.
+pub fn spawn_batch_install(
+    entries: Vec<DotfileEntry>,
+    force: bool,
+    keep_going: bool,
+) -> InstallHandle {
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(Cancel::default());
+
+    let flag = Arc::clone(&cancel);
+    thread::spawn(move || {
+        // Entries that failed or were skipped
+        let mut failed: Vec<&DotfileEntry> = vec![];
+        for entry in &entries {
+            let path = entry.path.clone();
+            let blocker = failed.iter().find(|failed| depends_on(entry, failed));
+            let skip = if flag.is_requested() {
+                Some("cancelled".to_string())
+            } else if let Some(blocker) = blocker {
+                Some(format!(
+                    "depends on {}, which was not installed",
+                    blocker.config.name
+                ))
+            } else if !failed.is_empty() && !keep_going {
+                Some("an earlier install failed".to_string())
+            } else {
+                None
+            };
+            if let Some(reason) = skip {
+                failed.push(entry);
+                let _ = tx.send(InstallEvent::Entry(path, EntryOutcome::Skipped(reason)));
+                continue;
+            }
+
+            let _ = tx.send(InstallEvent::Entry(path.clone(), EntryOutcome::Running));
+            let _ = tx.send(InstallEvent::Line(format!("==> {}", entry.config.name)));
+            let success = match run_install(entry, force, &tx, &flag) {
+                Ok(success) => success,
+                Err(e) => {
+                    let _ = tx.send(InstallEvent::Line(format!("error: {:#}", e)));
+                    false
+                }
+            };
+            let outcome = if success {
+                EntryOutcome::Installed
+            } else {
+                failed.push(entry);
+                EntryOutcome::Failed
+            };
+            let _ = tx.send(InstallEvent::Entry(path, outcome));
+        }
+        let _ = tx.send(InstallEvent::Finished(Ok(failed.is_empty())));
+    });
+
+    InstallHandle { events: rx, cancel }
+}
+
+fn run_install(
+    entry: &DotfileEntry,
+    force: bool,
+    tx: &Sender<InstallEvent>,
+    cancel: &Cancel,
+) -> Result<bool> {
+    let plan = plan_install(entry);
+
+    let dependencies = check_dependencies(&entry.config.dependencies);
+    let missing: Vec<&DepCheck> = dependencies
+        .iter()
+        .filter(|dep| !dep.is_satisfied())
+        .collect();
+    for dep in &missing {
+        let _ = tx.send(InstallEvent::Line(format!(
+            "dependency not satisfied: {}",
+            dep.spec
+        )));
+    }
+    if !missing.is_empty() && !force {
+        let _ = tx.send(InstallEvent::Line(
+            "install aborted (force install with I or --force)".to_string(),
+        ));
+        return Ok(false);
+    }
+
+    for line in deploy_targets(entry, &plan)? {
+        let _ = tx.send(InstallEvent::Line(line));
+    }
+
+    let success = match &plan.command {
+        Some(command) => {
+            let _ = tx.send(InstallEvent::Line(format!("$ {}", command.display())));
+            run_command(command, tx, cancel)?
+        }
+        None => true,
+    };
+
+    let mut state = InstallState::load()?;
+    state.record_install(entry, success);
+    state.save()?;
+
+    Ok(success)
+}
+
+/// Runs the command with piped output, forwarding lines until it exits or
+/// is cancelled. The command gets its own process group so that cancelling
+/// also kills whatever it started (`make`, `sleep`, ...), which would
+/// otherwise keep the pipes open.
+fn run_command(
+    command: &PlannedCommand,
+    tx: &Sender<InstallEvent>,
+    cancel: &Cancel,
+) -> Result<bool> {
+    let mut child = command
+        .to_command()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    cancel.group.store(child.id() as i32, Ordering::SeqCst);
+    // A cancel that came in while spawning did not see the group yet
+    if cancel.is_requested() {
+        cancel.kill_group();
+    }
+
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|out| forward_lines(out, tx.clone())),
+        child
+            .stderr
+            .take()
+            .map(|err| forward_lines(err, tx.clone())),
+    ];
+
+    let mut killed = false;
+    let status = loop {
+        if !killed && cancel.is_requested() {
+            let _ = tx.send(InstallEvent::Line("cancelled".to_string()));
+            killed = true;
+        }
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    cancel.group.store(0, Ordering::SeqCst);
+
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    Ok(status.success())
+}
+
+fn forward_lines(stream: impl Read + Send + 'static, tx: Sender<InstallEvent>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+            if tx.send(InstallEvent::Line(line)).is_err() {
+                break;
             }
-            cmd.current_dir(&entry.path);
-            cmd.output()?
         }
+    })
+}
+
+/// Works out the command for the entry's compiler, or `None` when there is
+/// nothing to run (no compiler, or a `make` package with neither install.sh
+/// nor a Makefile).
+fn compiler_command(entry: &DotfileEntry) -> Option<PlannedCommand> {
+    let (program, args): (&str, Vec<String>) = match entry.config.compiler.as_ref()? {
+        Compiler::Gcc { flags } => ("gcc", flags.clone().unwrap_or_default()),
         Compiler::Make { target } => {
             // Try to run install.sh directly if it exists, otherwise use make
             let install_script = entry.path.join("install.sh");
             if install_script.exists() {
-                let mut cmd = Command::new("bash");
-                cmd.arg("install.sh");
-                cmd.current_dir(&entry.path);
-                cmd.output()?
+                ("bash", vec!["install.sh".to_string()])
+            } else if entry.path.join("Makefile").exists() || entry.config.targets.is_empty() {
+                let target = target.clone().unwrap_or_else(|| "install".to_string());
+                ("make", vec![target])
             } else {
-                let mut cmd = Command::new("make");
-                if let Some(target) = target {
-                    cmd.arg(target);
-                } else {
-                    cmd.arg("install");
-                }
-                cmd.current_dir(&entry.path);
-                cmd.output()?
+                return None;
             }
         }
         Compiler::Cargo { release } => {
-            let mut cmd = Command::new("cargo");
-            cmd.arg("build");
+            let mut args = vec!["build".to_string()];
             if release.unwrap_or(false) {
-                cmd.arg("--release");
+                args.push("--release".to_string());
             }
-            cmd.current_dir(&entry.path);
-            cmd.output()?
+            ("cargo", args)
         }
         Compiler::Nix { flake } => {
-            let mut cmd = Command::new("nix-build");
+            let mut args = vec![];
             if flake.unwrap_or(false) {
-                cmd.arg("--flake");
+                args.push("--flake".to_string());
             }
-            cmd.current_dir(&entry.path);
-            cmd.output()?
+            ("nix-build", args)
         }
     };
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let combined = format!("{}\n{}", stdout, stderr);
-
-    Ok(InstallResult {
-        success: output.status.success(),
-        output: combined.trim().to_string(),
+    Some(PlannedCommand {
+        program: program.to_string(),
+        args,
+        cwd: entry.path.clone(),
     })
 }
+
+/// Carries out the deploy steps of a plan, backing up whatever they replace
+/// first. Returns one log line per file.
+fn deploy_targets(entry: &DotfileEntry, plan: &InstallPlan) -> Result<Vec<String>> {
+    let mut log = vec![];
+    let mut pending = vec![];
+
+    for step in &plan.steps {
+        let target = &step.target;
+        match step.action {
+            DeployAction::SourceMissing => {
+                log.push(format!("skipped {} (source missing)", target.file));
+            }
+            DeployAction::Unchanged => log.push(format!(
+                "unchanged {} -> {}",
+                target.file,
+                target.dest.display()
+            )),
+            DeployAction::InPlace => log.push(format!(
+                "in place {} ({})",
+                target.file,
+                target.dest.display()
+            )),
+            DeployAction::Deploy(strategy) => pending.push((target.clone(), strategy)),
+        }
+    }
+
+    let targets: Vec<DeployTarget> = pending.iter().map(|(target, _)| target.clone()).collect();
+    if let Some(snapshot) = backup::snapshot(entry, &targets)? {
+        log.push(format!(
+            "backed up {} target(s) to {}",
+            snapshot.manifest.files.len(),
+            snapshot.dir.display()
+        ));
+    }
+
+    for (target, strategy) in &pending {
+        let dest = target.dest.display();
+        if let Some(parent) = target.dest.parent() {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+        }
+
+        remove_existing(&target.dest).wrap_err_with(|| format!("could not replace {}", dest))?;
+        deploy_path(&target.source, &target.dest, *strategy).wrap_err_with(|| {
+            format!("could not deploy {} to {}", target.source.display(), dest)
+        })?;
+
+        log.push(format!(
+            "{} {} -> {}",
+            get_deploy_verb(*strategy),
+            target.file,
+            target.dest.display()
+        ));
+    }
+
+    Ok(log)
+}
+
+fn get_deploy_verb(strategy: DeployStrategy) -> &'static str {
+    match strategy {
+        DeployStrategy::Symlink => "linked",
+        DeployStrategy::Copy => "copied",
+        DeployStrategy::Hardlink => "hardlinked",
+    }
+}
+
+fn is_linked_to(dest: &Path, source: &Path) -> bool {
+    match (fs::read_link(dest), source.canonicalize()) {
+        (Ok(link), Ok(source)) => link == source,
+        _ => false,
+    }
+}
+
+/// Whether `dest` resolves to `source` without being a symlink itself, so
+/// replacing it would delete the source.
+fn is_source_itself(dest: &Path, source: &Path) -> bool {
+    let is_link = fs::symlink_metadata(dest).is_ok_and(|meta| meta.file_type().is_symlink());
+    match (dest.canonicalize(), source.canonicalize()) {
+        (Ok(dest), Ok(source)) => !is_link && dest == source,
+        _ => false,
+    }
+}
+
+fn remove_existing(dest: &Path) -> Result<()> {
+    match fs::symlink_metadata(dest) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(dest)?,
+        Ok(_) => fs::remove_file(dest)?,
+        Err(_) => {}
+    }
+    Ok(())
+}
+
+fn deploy_path(source: &Path, dest: &Path, strategy: DeployStrategy) -> Result<()> {
+    match strategy {
+        DeployStrategy::Symlink => std::os::unix::fs::symlink(source.canonicalize()?, dest)?,
+        _ if source.is_dir() => {
+            // Copies and hardlinks can't target a directory, so mirror the tree
+            fs::create_dir_all(dest)?;
+            for child in fs::read_dir(source)?.flatten() {
+                deploy_path(&child.path(), &dest.join(child.file_name()), strategy)?;
+            }
+        }
+        DeployStrategy::Copy => {
+            fs::copy(source, dest)?;
+        }
+        DeployStrategy::Hardlink => fs::hard_link(source, dest)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DotfileConfig;
+
+    fn entry(dir: &str, name: &str, requires: &[&str]) -> DotfileEntry {
+        let mut config: DotfileConfig = toml::from_str(&format!("name = {:?}", name)).unwrap();
+        config.requires = requires.iter().map(|r| r.to_string()).collect();
+        DotfileEntry {
+            config,
+            path: PathBuf::from("/dots").join(dir),
+        }
+    }
+
+    fn names(entries: &[DotfileEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.config.name.as_str()).collect()
+    }
+
+    #[test]
+    fn install_order_keeps_independent_entries_in_place() {
+        let entries = vec![
+            entry("c", "c", &[]),
+            entry("a", "a", &[]),
+            entry("b", "b", &[]),
+        ];
+        assert_eq!(names(&install_order(entries).unwrap()), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn install_order_puts_required_entries_first() {
+        let entries = vec![
+            entry("bar", "bar", &["Shell Colors"]),
+            entry("other", "other", &[]),
+            entry("colors", "shell colors", &[]),
+        ];
+        assert_eq!(
+            names(&install_order(entries).unwrap()),
+            ["other", "shell colors", "bar"]
+        );
+    }
+
+    #[test]
+    fn install_order_matches_directory_names() {
+        let entries = vec![
+            entry("kitty-blue", "Kitty Blue", &["base16"]),
+            entry("base16", "Base16 Shell", &[]),
+        ];
+        assert_eq!(
+            names(&install_order(entries).unwrap()),
+            ["Base16 Shell", "Kitty Blue"]
+        );
+    }
+
+    #[test]
+    fn install_order_ignores_path_dependencies() {
+        let mut kitty = entry("kitty", "kitty", &[]);
+        kitty.config.dependencies = vec!["red".to_string()];
+        let entries = vec![kitty, entry("red", "red", &[])];
+        assert_eq!(names(&install_order(entries).unwrap()), ["kitty", "red"]);
+    }
+
+    #[test]
+    fn install_order_rejects_cycles() {
+        let entries = vec![
+            entry("free", "free", &[]),
+            entry("a", "a", &["b"]),
+            entry("b", "b", &["a"]),
+        ];
+        let error = install_order(entries).unwrap_err();
+        assert_eq!(error, "dependency cycle between a, b");
+    }
+}
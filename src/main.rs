@@ -1,10 +1,30 @@
 mod app;
+mod backup;
+mod cli;
 mod config;
+mod deps;
+mod diff;
+mod graphics;
+mod highlight;
 mod installer;
+mod palette;
+mod paths;
+mod profile;
+mod scan_cache;
 mod scanner;
+mod search;
+mod settings;
+mod state;
+mod status;
+mod tree;
 mod ui;
+mod validate;
+mod watch;
 
 use app::{App, View};
+use clap::Parser;
+use cli::{Cli, Command};
+use graphics::ImageOutput;
 use color_eyre::Result;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent},
@@ -13,16 +33,20 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, stdout};
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     color_eyre::install()?;
 
-    // Check for CLI flags
-    let args: Vec<String> = std::env::args().collect();
-
-    if args.len() > 1 && (args[1] == "--list" || args[1] == "-l") {
-        return list_dotfiles();
+    let mut cli = Cli::parse();
+    let format = cli.output_format();
+    if cli.list {
+        return Ok(cli::run(Command::List, format, &cli));
+    }
+    if let Some(command) = cli.command.take() {
+        return Ok(cli::run(command, format, &cli));
     }
+    let scanner = cli::scanner(&cli.paths, cli.rescan)?;
 
     // Setup terminal
     enable_raw_mode()?;
@@ -30,7 +54,7 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
     // Create app
-    let mut app = App::new()?;
+    let mut app = App::new(scanner)?;
 
     // Run the app
     let result = run_app(&mut terminal, &mut app);
@@ -39,85 +63,144 @@ fn main() -> Result<()> {
     disable_raw_mode()?;
     stdout().execute(LeaveAlternateScreen)?;
 
-    result
-}
-
-fn list_dotfiles() -> Result<()> {
-    use crate::config::get_compiler_name;
-    use crate::scanner::DotfileScanner;
-
-    let scanner = DotfileScanner::new();
-    let dotfiles = scanner.scan()?;
-
-    if dotfiles.is_empty() {
-        println!("No dotfiles found.");
-        println!("\nCreate a dotwell.toml or dotwell.json file in your dotfiles directory.");
-        return Ok(());
-    }
-
-    println!("Found {} dotfile configuration{}:\n",
-        dotfiles.len(),
-        if dotfiles.len() == 1 { "" } else { "s" });
-
-    for (i, entry) in dotfiles.iter().enumerate() {
-        let compiler = get_compiler_name(&entry.config.compiler);
-        println!("{}. {} [{}]", i + 1, entry.config.name, compiler);
-        println!("   {}", entry.config.description);
-        println!("   Path: {}", entry.path.display());
-        println!("   Category: {}", entry.config.category);
-        println!("   Dependencies: {}", entry.config.dependencies.join(", "));
-        println!();
-    }
-
-    Ok(())
+    result.map(|_| ExitCode::SUCCESS)
 }
 
 fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
+    let mut images = ImageOutput::new(app.graphics);
+
     loop {
+        app.poll_install();
+        app.poll_changes();
+        app.poll_dep_checks();
+
+        let mut image_area = None;
         terminal.draw(|frame| {
-            ui::render(frame, app);
+            image_area = ui::render(frame, app);
         })?;
 
+        let screenshot = app.screenshot.as_ref().and_then(|s| s.as_ref().ok());
+        if images.show(&mut stdout(), screenshot.zip(image_area))? {
+            terminal.clear()?;
+        }
+
         // Handle input
         if event::poll(std::time::Duration::from_millis(100))? {
             if let Event::Key(KeyEvent { code, .. }) = event::read()? {
+                if app.pending_rollback.is_some() {
+                    match code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            if let Err(e) = app.confirm_rollback() {
+                                app.set_notice(format!("rollback failed: {:#}", e));
+                            }
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => app.cancel_rollback(),
+                        _ => {}
+                    }
+                    continue;
+                }
+
                 match app.view {
                     View::Home => match code {
                         KeyCode::Char('q') | KeyCode::Esc => app.quit(),
                         KeyCode::Char('b') => app.go_to_view(View::Browse),
+                        KeyCode::Char('p') => app.open_problems(),
+                        KeyCode::Char('P') => app.open_profiles(),
+                        KeyCode::Char('r') => app.reload(),
+                        _ => {}
+                    },
+                    View::Browse if app.searching => match code {
+                        KeyCode::Esc => app.clear_filter(),
+                        KeyCode::Enter => app.finish_search(),
+                        KeyCode::Backspace => app.pop_search_char(),
+                        KeyCode::Down => app.next_item(),
+                        KeyCode::Up => app.previous_item(),
+                        KeyCode::Char(c) => app.push_search_char(c),
                         _ => {}
                     },
                     View::Browse => match code {
                         KeyCode::Char('q') => app.quit(),
+                        KeyCode::Esc if app.filter.is_active() => app.clear_filter(),
                         KeyCode::Esc | KeyCode::Char('b') => app.go_back(),
+                        KeyCode::Char('/') => app.start_search(),
+                        KeyCode::Char('c') => app.cycle_category_filter(),
+                        KeyCode::Char('t') => app.cycle_compiler_filter(),
                         KeyCode::Down | KeyCode::Char('j') => app.next_item(),
                         KeyCode::Up | KeyCode::Char('k') => app.previous_item(),
-                        KeyCode::Char('i') => {
-                            if let Err(e) = app.install_selected() {
-                                eprintln!("Install error: {}", e);
-                            }
-                        }
-                        KeyCode::Enter => {
-                            if !app.dotfiles.is_empty() {
-                                app.go_to_view(View::Preview);
-                            }
-                        }
+                        KeyCode::Left | KeyCode::Char('h') => app.collapse_group(),
+                        KeyCode::Right | KeyCode::Char('l') => app.expand_group(),
+                        KeyCode::Enter if app.on_group() => app.toggle_group(),
+                        KeyCode::Char('D') => app.toggle_dry_run(),
+                        KeyCode::Char('C') => app.toggle_keep_going(),
+                        KeyCode::Char('p') => app.open_problems(),
+                        KeyCode::Char('P') => app.open_profiles(),
+                        KeyCode::Char('r') => app.reload(),
+                        KeyCode::Char('u') => app.rollback_last(),
+                        KeyCode::Char(' ') => app.toggle_mark(),
+                        KeyCode::Char('M') => app.install_marked(false),
+                        KeyCode::Char('i') => app.install_selected(false),
+                        KeyCode::Char('I') => app.install_selected(true),
+                        KeyCode::Enter => app.open_preview(),
                         _ => {}
                     },
                     View::Preview => match code {
                         KeyCode::Char('q') => app.quit(),
                         KeyCode::Esc => app.go_back(),
-                        KeyCode::Char('i') | KeyCode::Enter => {
-                            if let Err(e) = app.install_selected() {
-                                // Show error to user somehow
-                                eprintln!("Install error: {}", e);
+                        KeyCode::Char('d') => app.open_diff(),
+                        KeyCode::Char('D') => app.toggle_dry_run(),
+                        KeyCode::Down | KeyCode::Char('j') => app.select_preview_file(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.select_preview_file(-1),
+                        KeyCode::Char('J') => app.scroll_file(1),
+                        KeyCode::Char('K') => app.scroll_file(-1),
+                        KeyCode::PageDown => app.scroll_file(20),
+                        KeyCode::PageUp => app.scroll_file(-20),
+                        KeyCode::Char('i') | KeyCode::Enter => app.install_selected(false),
+                        KeyCode::Char('I') => app.install_selected(true),
+                        _ => {}
+                    },
+                    View::Diff => match code {
+                        KeyCode::Char('q') => app.quit(),
+                        KeyCode::Esc => app.go_back(),
+                        KeyCode::Down | KeyCode::Char('j') => app.scroll_diff(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.scroll_diff(-1),
+                        KeyCode::PageDown => app.scroll_diff(20),
+                        KeyCode::PageUp => app.scroll_diff(-20),
+                        KeyCode::Char('i') => app.install_selected(false),
+                        KeyCode::Char('I') => app.install_selected(true),
+                        _ => {}
+                    },
+                    View::Problems => match code {
+                        KeyCode::Char('q') => app.quit(),
+                        KeyCode::Esc => app.go_back(),
+                        KeyCode::Down | KeyCode::Char('j') => app.scroll_problems(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.scroll_problems(-1),
+                        KeyCode::Char('r') => app.reload(),
+                        _ => {}
+                    },
+                    View::Profiles => match code {
+                        KeyCode::Char('q') => app.quit(),
+                        KeyCode::Esc => app.go_back(),
+                        KeyCode::Down | KeyCode::Char('j') => app.move_profile_cursor(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.move_profile_cursor(-1),
+                        KeyCode::Char('D') => app.toggle_dry_run(),
+                        KeyCode::Char('C') => app.toggle_keep_going(),
+                        KeyCode::Char('r') => app.reload(),
+                        KeyCode::Enter | KeyCode::Char('a') => {
+                            if let Err(e) = app.apply_selected_profile(false) {
+                                app.set_notice(format!("apply failed: {:#}", e));
+                            }
+                        }
+                        KeyCode::Char('A') => {
+                            if let Err(e) = app.apply_selected_profile(true) {
+                                app.set_notice(format!("apply failed: {:#}", e));
                             }
                         }
                         _ => {}
                     },
                     View::Installing => match code {
                         KeyCode::Char('q') => app.quit(),
-                        KeyCode::Esc | KeyCode::Enter => app.go_back(),
+                        KeyCode::Char('c') => app.cancel_install(),
+                        KeyCode::Esc | KeyCode::Enter if !app.is_installing() => app.go_back(),
                         _ => {}
                     },
                 }
@@ -125,6 +208,9 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
         }
 
         if app.should_quit {
+            // The install's process group would outlive the TUI otherwise
+            app.cancel_install();
+            images.show(&mut stdout(), None)?;
             break;
         }
     }
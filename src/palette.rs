@@ -0,0 +1,313 @@
+use crate::config::DotfileEntry;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// More colors than this is a stylesheet, not a palette.
+const MAX_SWATCHES: usize = 32;
+/// Files bigger than this are not scanned for colors.
+const MAX_SCAN_BYTES: u64 = 256 * 1024;
+
+/// One color of a theme, named after the key it was assigned to when known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swatch {
+    pub name: Option<String>,
+    pub rgb: (u8, u8, u8),
+}
+
+impl Swatch {
+    pub fn hex(&self) -> String {
+        let (r, g, b) = self.rgb;
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// The entry's palette: its `[palette]` table when it has one, otherwise
+/// the colors found in its files.
+pub fn entry_palette(entry: &DotfileEntry) -> Vec<Swatch> {
+    if !entry.config.palette.is_empty() {
+        let mut swatches: Vec<Swatch> = entry
+            .config
+            .palette
+            .iter()
+            .filter_map(|(name, value)| {
+                Some(Swatch {
+                    name: Some(name.clone()),
+                    rgb: parse_value(value)?,
+                })
+            })
+            .collect();
+        swatches.sort_by_key(|swatch| natural_key(swatch.name.as_deref().unwrap_or("")));
+        return swatches;
+    }
+
+    let mut swatches = vec![];
+    for file in &entry.config.files {
+        collect_from_path(&entry.path.join(file), &mut swatches);
+    }
+    order(dedup(swatches))
+}
+
+fn collect_from_path(path: &Path, swatches: &mut Vec<Swatch>) {
+    if path.is_dir() {
+        let Ok(read_dir) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<_> = read_dir.flatten().collect();
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            collect_from_path(&child.path(), swatches);
+        }
+        return;
+    }
+
+    if fs::metadata(path).map_or(true, |m| m.len() > MAX_SCAN_BYTES) {
+        return;
+    }
+    if let Ok(content) = fs::read_to_string(path) {
+        swatches.extend(extract_colors(&content));
+    }
+}
+
+/// Every color literal in `content`: `#rgb`/`#rrggbb`/`#rrggbbaa`, `0xrrggbb`
+/// and `rgb()`/`rgba()`. A color that is the value of a `key = value`,
+/// `key: value` or `key value` line (Xresources `*color4`, kitty `color4`,
+/// alacritty `blue = '#...'`) is named after the key, and so is one set by
+/// a `#define NAME VALUE` line (Xresources through cpp).
+fn extract_colors(content: &str) -> Vec<Swatch> {
+    let mut swatches = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        // Checked first, since a leading `#` otherwise marks a comment
+        let line = match line.strip_prefix("#define") {
+            Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start(),
+            _ => line,
+        };
+        let commented = ["//", "--", "!", ";"].iter().any(|c| line.starts_with(c))
+            || (line.starts_with('#') && parse_color(line).is_none());
+        if commented {
+            continue;
+        }
+
+        let colors = line_colors(line);
+        let name = (colors.len() == 1).then(|| line_key(line)).flatten();
+        swatches.extend(colors.into_iter().map(|rgb| Swatch {
+            name: name.clone(),
+            rgb,
+        }));
+    }
+    swatches
+}
+
+fn line_colors(line: &str) -> Vec<(u8, u8, u8)> {
+    let mut colors = vec![];
+    let mut offset = 0;
+    while offset < line.len() {
+        let rest = &line[offset..];
+        let at_boundary = line[..offset]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_ascii_alphanumeric());
+        match parse_color(rest).filter(|_| at_boundary) {
+            Some((rgb, len)) => {
+                colors.push(rgb);
+                offset += len;
+            }
+            None => offset += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    colors
+}
+
+/// Parses a `[palette]` value, which must be exactly one color.
+pub fn parse_value(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim();
+    parse_color(value)
+        .filter(|(_, len)| *len == value.len())
+        .map(|(rgb, _)| rgb)
+}
+
+/// Parses a color at the start of `text`, returning it and its length.
+fn parse_color(text: &str) -> Option<((u8, u8, u8), usize)> {
+    if let Some(hex) = text.strip_prefix('#') {
+        return parse_hex(hex).map(|(rgb, len)| (rgb, len + 1));
+    }
+    if let Some(hex) = text.strip_prefix("0x") {
+        return parse_hex(hex)
+            .filter(|(_, len)| *len == 6)
+            .map(|(rgb, len)| (rgb, len + 2));
+    }
+    let lower = text.get(..5)?.to_ascii_lowercase();
+    if lower.starts_with("rgb(") || lower.starts_with("rgba(") {
+        return parse_rgb_function(text);
+    }
+    None
+}
+
+fn parse_hex(hex: &str) -> Option<((u8, u8, u8), usize)> {
+    let len = hex.chars().take_while(|c| c.is_ascii_hexdigit()).count();
+    if hex[len..].starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let channel = |i: usize, width: usize| u8::from_str_radix(&hex[i..i + width], 16).ok();
+    let rgb = match len {
+        3 => {
+            let expand = |i| channel(i, 1).map(|v| v * 17);
+            (expand(0)?, expand(1)?, expand(2)?)
+        }
+        6 | 8 => (channel(0, 2)?, channel(2, 2)?, channel(4, 2)?),
+        _ => return None,
+    };
+    Some((rgb, len))
+}
+
+fn parse_rgb_function(text: &str) -> Option<((u8, u8, u8), usize)> {
+    let open = text.find('(')?;
+    let close = text.find(')')?;
+    let mut channels = text[open + 1..close]
+        .split([',', ' ', '/'])
+        .filter(|part| !part.is_empty())
+        .map(|part| part.trim().parse::<f32>().ok());
+    let mut next = || Some(channels.next()??.clamp(0.0, 255.0).round() as u8);
+    Some(((next()?, next()?, next()?), close + 1))
+}
+
+/// The key of a `key = value` style line, without Xresources' `*` / `.`
+/// prefixes and quotes.
+fn line_key(line: &str) -> Option<String> {
+    let end = line.find(|c: char| c == '=' || c == ':' || c.is_whitespace())?;
+    let key = line[..end]
+        .trim_start_matches(['*', '.'])
+        .trim_matches(['"', '\''])
+        .rsplit('.')
+        .next()?;
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    valid.then(|| key.to_string())
+}
+
+/// Keeps the first swatch of each name, then the unnamed colors that no
+/// named swatch already shows. Themes often repeat a color under several
+/// names (`background` and `color0`), so named swatches are not merged.
+fn dedup(swatches: Vec<Swatch>) -> Vec<Swatch> {
+    let mut names = HashSet::new();
+    let (named, unnamed): (Vec<Swatch>, Vec<Swatch>) =
+        swatches.into_iter().partition(|swatch| swatch.name.is_some());
+    let mut unique: Vec<Swatch> = named
+        .into_iter()
+        .filter(|swatch| names.insert(swatch.name.clone()))
+        .collect();
+
+    let mut seen: HashSet<_> = unique.iter().map(|swatch| swatch.rgb).collect();
+    unique.extend(unnamed.into_iter().filter(|swatch| seen.insert(swatch.rgb)));
+    unique
+}
+
+/// Puts `color0`..`color15` first in terminal order, then everything else
+/// as found, capped at `MAX_SWATCHES`.
+fn order(swatches: Vec<Swatch>) -> Vec<Swatch> {
+    let (mut numbered, rest): (Vec<Swatch>, Vec<Swatch>) = swatches
+        .into_iter()
+        .partition(|swatch| terminal_index(swatch).is_some());
+    numbered.sort_by_key(terminal_index);
+    numbered.extend(rest);
+    numbered.truncate(MAX_SWATCHES);
+    numbered
+}
+
+fn terminal_index(swatch: &Swatch) -> Option<u32> {
+    swatch
+        .name
+        .as_deref()?
+        .strip_prefix("color")?
+        .parse()
+        .ok()
+}
+
+/// Sorts `color2` before `color10`.
+fn natural_key(name: &str) -> (String, u64) {
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (stem, number) = name.split_at(name.len() - digits);
+    (stem.to_string(), number.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swatch(name: Option<&str>, rgb: (u8, u8, u8)) -> Swatch {
+        Swatch {
+            name: name.map(str::to_string),
+            rgb,
+        }
+    }
+
+    #[test]
+    fn parse_value_reads_hex() {
+        assert_eq!(parse_value("#1d1f21"), Some((0x1d, 0x1f, 0x21)));
+        assert_eq!(parse_value("  #fff "), Some((255, 255, 255)));
+        assert_eq!(parse_value("#1d1f21cc"), Some((0x1d, 0x1f, 0x21)));
+        assert_eq!(parse_value("0xcc6666"), Some((0xcc, 0x66, 0x66)));
+    }
+
+    #[test]
+    fn parse_value_reads_rgb_functions() {
+        assert_eq!(parse_value("rgb(29, 31, 33)"), Some((29, 31, 33)));
+        assert_eq!(parse_value("RGBA(29 31 33 / 0.5)"), Some((29, 31, 33)));
+        assert_eq!(parse_value("rgb(300, 0, 0)"), Some((255, 0, 0)));
+    }
+
+    #[test]
+    fn parse_value_rejects_anything_but_one_color() {
+        assert_eq!(parse_value("#12345"), None);
+        assert_eq!(parse_value("#1d1f21 #ffffff"), None);
+        assert_eq!(parse_value("#abcdefg"), None);
+        assert_eq!(parse_value("blue"), None);
+    }
+
+    #[test]
+    fn extract_colors_names_colors_after_their_key() {
+        let content = "*color4: #81a2be\nbackground = \"#1d1f21\"\ncolor1 #cc6666\n";
+        assert_eq!(
+            extract_colors(content),
+            vec![
+                swatch(Some("color4"), (0x81, 0xa2, 0xbe)),
+                swatch(Some("background"), (0x1d, 0x1f, 0x21)),
+                swatch(Some("color1"), (0xcc, 0x66, 0x66)),
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_colors_reads_defines() {
+        let content = "#define base00 #1d1f21\n*background: base00\n";
+        assert_eq!(
+            extract_colors(content),
+            vec![swatch(Some("base00"), (0x1d, 0x1f, 0x21))]
+        );
+    }
+
+    #[test]
+    fn extract_colors_skips_comments() {
+        let content = "# color0 #000000\n! color1 #111111\n// #222222\n-- #333333\n; #444444\n";
+        assert_eq!(extract_colors(content), vec![]);
+    }
+
+    #[test]
+    fn extract_colors_keeps_a_line_that_is_only_a_color() {
+        assert_eq!(
+            extract_colors("#282a36\n"),
+            vec![swatch(None, (0x28, 0x2a, 0x36))]
+        );
+    }
+
+    #[test]
+    fn extract_colors_leaves_lines_with_several_colors_unnamed() {
+        assert_eq!(
+            extract_colors("gradient = #000000 #ffffff\n"),
+            vec![swatch(None, (0, 0, 0)), swatch(None, (255, 255, 255))]
+        );
+    }
+}
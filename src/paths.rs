@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+/// Returns `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Returns `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn state_home() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// Root directory for everything Dotwell persists between runs.
+pub fn dotwell_state_dir() -> PathBuf {
+    state_home().join("dotwell")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::home_dir().unwrap_or_default().join(fallback),
+    }
+}
+
+/// Expands a leading `~` and `$VAR` / `${VAR}` references in a target path.
+/// XDG base directories fall back to their spec defaults when unset.
+pub fn expand(path: &str) -> PathBuf {
+    let mut expanded = String::new();
+
+    let rest = if path == "~" || path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            expanded.push_str(&home.to_string_lossy());
+        }
+        &path[1..]
+    } else {
+        path
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if braced && chars.peek() == Some(&'}') {
+            chars.next();
+        }
+
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        expanded.push_str(&lookup_var(&name));
+    }
+
+    PathBuf::from(expanded)
+}
+
+fn lookup_var(name: &str) -> String {
+    let dir = match name {
+        "XDG_CONFIG_HOME" => config_home(),
+        "XDG_STATE_HOME" => state_home(),
+        "HOME" => dirs::home_dir().unwrap_or_default(),
+        _ => return std::env::var(name).unwrap_or_default(),
+    };
+    dir.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> String {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn expands_leading_tilde() {
+        assert_eq!(expand("~"), PathBuf::from(home()));
+        assert_eq!(
+            expand("~/.vimrc"),
+            PathBuf::from(format!("{}/.vimrc", home()))
+        );
+    }
+
+    #[test]
+    fn keeps_tilde_that_is_not_a_home_prefix() {
+        assert_eq!(expand("~user/.vimrc"), PathBuf::from("~user/.vimrc"));
+        assert_eq!(expand("/etc/~/x"), PathBuf::from("/etc/~/x"));
+    }
+
+    #[test]
+    fn expands_variables() {
+        std::env::set_var("DOTWELL_TEST_EXPAND", "/opt/themes");
+        assert_eq!(
+            expand("$DOTWELL_TEST_EXPAND/nord"),
+            PathBuf::from("/opt/themes/nord")
+        );
+        assert_eq!(
+            expand("${DOTWELL_TEST_EXPAND}nord"),
+            PathBuf::from("/opt/themesnord")
+        );
+        assert_eq!(
+            expand("$HOME/.bashrc"),
+            PathBuf::from(format!("{}/.bashrc", home()))
+        );
+    }
+
+    #[test]
+    fn unset_variable_expands_to_nothing() {
+        std::env::remove_var("DOTWELL_TEST_UNSET");
+        assert_eq!(
+            expand("$DOTWELL_TEST_UNSET/foot.ini"),
+            PathBuf::from("/foot.ini")
+        );
+    }
+
+    #[test]
+    fn lone_dollar_is_kept() {
+        assert_eq!(expand("/tmp/$/x"), PathBuf::from("/tmp/$/x"));
+    }
+}
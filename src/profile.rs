@@ -0,0 +1,152 @@
+use crate::config::{DotfileEntry, ManifestProblem};
+use crate::settings::Settings;
+use crate::state::InstallState;
+use crate::status::{entry_status, Status};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Profile file read from the top of each scan root.
+pub const PROFILE_FILE: &str = "dwell-profile.toml";
+
+/// A named set of entries applied together, e.g. a bar, terminal, wm and
+/// gtk theme that make up one desktop look.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    pub description: String,
+    /// Entry names, matched like `dwell install` arguments.
+    pub entries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ProfileConfig,
+    /// The settings file or `dwell-profile.toml` defining it.
+    pub source: PathBuf,
+}
+
+/// What applying a profile would do to one of its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileAction {
+    /// Installed, unmodified and not replaced by another entry since.
+    UpToDate,
+    Install,
+    /// No scanned entry has this name.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileItem {
+    /// The name as written in the profile.
+    pub name: String,
+    pub entry: Option<DotfileEntry>,
+    pub action: ProfileAction,
+}
+
+/// Profiles from the user settings, then from a `dwell-profile.toml` in each
+/// root. A name defined twice keeps its first definition. Files that fail
+/// to parse are reported as problems.
+pub fn load_profiles(
+    settings: &Settings,
+    roots: &[PathBuf],
+) -> (Vec<Profile>, Vec<ManifestProblem>) {
+    let mut profiles: Vec<Profile> = vec![];
+    let mut problems = vec![];
+    let mut add = |defined: BTreeMap<String, ProfileConfig>, source: &Path| {
+        for (name, config) in defined {
+            if !profiles.iter().any(|profile| profile.name == name) {
+                profiles.push(Profile {
+                    name,
+                    config,
+                    source: source.to_path_buf(),
+                });
+            }
+        }
+    };
+
+    add(settings.profiles.clone(), &Settings::path());
+    for root in roots {
+        let path = root.join(PROFILE_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        match toml::from_str(&content) {
+            Ok(defined) => add(defined, &path),
+            Err(e) => problems.push(ManifestProblem::from_toml_error(&path, &content, &e)),
+        }
+    }
+    (profiles, problems)
+}
+
+pub fn find_profile<'a>(profiles: &'a [Profile], name: &str) -> Option<&'a Profile> {
+    profiles
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+/// Works out which of the profile's entries differ from the current state.
+pub fn plan_profile(
+    profile: &Profile,
+    dotfiles: &[DotfileEntry],
+    state: &InstallState,
+) -> Vec<ProfileItem> {
+    profile
+        .config
+        .entries
+        .iter()
+        .map(|name| {
+            let entry = dotfiles
+                .iter()
+                .find(|entry| entry.config.name.eq_ignore_ascii_case(name));
+            let action = match entry {
+                None => ProfileAction::Unknown,
+                Some(entry) if is_up_to_date(entry, state) => ProfileAction::UpToDate,
+                Some(_) => ProfileAction::Install,
+            };
+            ProfileItem {
+                name: name.clone(),
+                entry: entry.cloned(),
+                action,
+            }
+        })
+        .collect()
+}
+
+fn is_up_to_date(entry: &DotfileEntry, state: &InstallState) -> bool {
+    let Some(installed) = state.get(entry).filter(|installed| installed.success) else {
+        return false;
+    };
+    // Entries sharing a category can all be in place; only one installed
+    // later over the same target takes it away
+    let displaced = state.entries.values().any(|other| {
+        other.path != installed.path
+            && other.success
+            && other.recency() > installed.recency()
+            && other
+                .files
+                .iter()
+                .any(|file| installed.files.iter().any(|ours| ours.dest == file.dest))
+    });
+    !displaced && entry_status(entry, state).status == Status::Clean
+}
+
+/// Whether the profile is the recorded one and nothing has drifted since.
+pub fn is_applied(profile: &Profile, items: &[ProfileItem], state: &InstallState) -> bool {
+    state.active_profile.as_deref() == Some(profile.name.as_str())
+        && items
+            .iter()
+            .all(|item| item.action == ProfileAction::UpToDate)
+}
+
+/// Remembers `name` as the applied profile.
+pub fn record_applied(name: &str) -> Result<()> {
+    let mut state = InstallState::load()?;
+    state.active_profile = Some(name.to_string());
+    state.save()
+}
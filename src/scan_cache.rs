@@ -0,0 +1,170 @@
+use crate::config::{DotfileConfig, ManifestProblem};
+use crate::paths;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Per-directory ignore files, read with gitignore semantics.
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".dwellignore"];
+
+/// What the scanner learned about each directory on the last walk, so
+/// unchanged directories need neither a `read_dir` nor a manifest parse.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanCache {
+    pub dirs: HashMap<PathBuf, CachedDir>,
+}
+
+/// A directory listing, valid for as long as the directory's mtime matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedDir {
+    pub mtime: SystemTime,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subdirs: Vec<Subdir>,
+    /// Names of the ignore files present, read again on every scan.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<CachedManifest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subdir {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub symlink: bool,
+}
+
+/// A parsed manifest, valid for as long as neither the manifest's own mtime
+/// nor its directory's changes. The default `files` list is taken from the
+/// directory listing, so it goes stale with the directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedManifest {
+    pub file: PathBuf,
+    pub mtime: Option<SystemTime>,
+    pub loaded: Result<DotfileConfig, ManifestProblem>,
+}
+
+impl ScanCache {
+    pub fn path() -> PathBuf {
+        paths::dotwell_state_dir().join("scan-cache.json")
+    }
+
+    /// Loads the cache; a missing or unreadable cache just means a full walk.
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename so a concurrent run never reads half a cache
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Replaces everything cached under `roots` with the directories just walked.
+    pub fn update(&mut self, roots: &[PathBuf], walked: Vec<(PathBuf, CachedDir)>) {
+        self.dirs
+            .retain(|dir, _| !roots.iter().any(|root| dir.starts_with(root)));
+        self.dirs.extend(walked);
+    }
+}
+
+impl CachedDir {
+    /// Lists `dir` and parses its manifest again.
+    pub fn read(dir: &Path, mtime: SystemTime) -> Self {
+        let mut subdirs = vec![];
+        let mut ignore_files = vec![];
+        let (mut has_toml, mut has_json) = (false, false);
+
+        if let Ok(read_dir) = fs::read_dir(dir) {
+            for entry in read_dir.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                match name.as_str() {
+                    "dotwell.toml" => has_toml = true,
+                    "dotwell.json" => has_json = true,
+                    _ if IGNORE_FILES.contains(&name.as_str()) => ignore_files.push(name),
+                    _ => {
+                        let Ok(file_type) = entry.file_type() else {
+                            continue;
+                        };
+                        if file_type.is_dir() {
+                            subdirs.push(Subdir {
+                                name,
+                                symlink: false,
+                            });
+                        } else if file_type.is_symlink() && entry.path().is_dir() {
+                            subdirs.push(Subdir {
+                                name,
+                                symlink: true,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        subdirs.sort_by(|a, b| a.name.cmp(&b.name));
+        ignore_files.sort();
+
+        // TOML wins when both exist
+        let manifest_file = if has_toml {
+            Some(dir.join("dotwell.toml"))
+        } else if has_json {
+            Some(dir.join("dotwell.json"))
+        } else {
+            None
+        };
+
+        Self {
+            mtime,
+            subdirs,
+            ignore_files,
+            manifest: manifest_file.map(|file| CachedManifest::load(file, None)),
+        }
+    }
+
+    /// Re-checks the manifest of an otherwise unchanged directory, since
+    /// editing a file in place does not touch its directory's mtime.
+    pub fn refresh(&self) -> Self {
+        let mut dir = self.clone();
+        dir.manifest = self
+            .manifest
+            .as_ref()
+            .map(|manifest| CachedManifest::load(manifest.file.clone(), Some(manifest)));
+        dir
+    }
+}
+
+impl CachedManifest {
+    fn load(file: PathBuf, previous: Option<&CachedManifest>) -> Self {
+        let mtime = fs::metadata(&file).and_then(|meta| meta.modified()).ok();
+        if let Some(previous) = previous {
+            if previous.file == file && mtime.is_some() && previous.mtime == mtime {
+                return previous.clone();
+            }
+        }
+
+        let loaded = if file.extension().is_some_and(|ext| ext == "json") {
+            DotfileConfig::from_json(&file)
+        } else {
+            DotfileConfig::from_toml(&file)
+        };
+        Self {
+            file,
+            mtime,
+            loaded,
+        }
+    }
+}
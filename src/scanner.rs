@@ -1,14 +1,120 @@
-use crate::config::{DotfileConfig, DotfileEntry};
+use crate::config::{DotfileEntry, ManifestProblem};
+use crate::paths;
+use crate::scan_cache::{CachedDir, ScanCache};
+use crate::settings::{ScanSettings, Settings};
 use color_eyre::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// What one directory subtree contributed to a scan.
+#[derive(Default)]
+struct Walk {
+    entries: Vec<DotfileEntry>,
+    problems: Vec<ManifestProblem>,
+    dirs: Vec<(PathBuf, CachedDir)>,
+}
+
+impl Walk {
+    fn append(&mut self, other: Walk) {
+        self.entries.extend(other.entries);
+        self.problems.extend(other.problems);
+        self.dirs.extend(other.dirs);
+    }
+}
+
+/// Entries found by a scan, plus every manifest that failed to load.
+pub struct ScanReport {
+    pub entries: Vec<DotfileEntry>,
+    pub problems: Vec<ManifestProblem>,
+    /// Every directory the scan walked.
+    pub dirs: Vec<PathBuf>,
+}
+
+/// Directory names that are never worth descending into.
+const SKIPPED_DIRS: [&str; 3] = ["target", "node_modules", "build"];
 
 pub struct DotfileScanner {
     search_paths: Vec<PathBuf>,
+    exclude: GlobSet,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    use_cache: bool,
+    /// Why the user's settings were not used, if they weren't.
+    warning: Option<String>,
 }
 
 impl DotfileScanner {
-    pub fn new() -> Self {
+    /// Scanner for the roots and options in the user's settings.
+    pub fn new() -> Result<Self> {
+        Self::from_settings(&Settings::load()?.scan)
+    }
+
+    /// Scanner for `paths` only, keeping the other scan settings. Settings
+    /// that fail to load fall back to the defaults, with a `warning`, since
+    /// the paths alone are enough to scan.
+    pub fn with_paths(paths: Vec<PathBuf>) -> Result<Self> {
+        let mut scanner = match Self::new() {
+            Ok(scanner) => scanner,
+            Err(e) => {
+                let mut scanner = Self::from_settings(&ScanSettings::default())?;
+                scanner.warning = Some(format!("{}; using the default scan settings", e));
+                scanner
+            }
+        };
+        scanner.search_paths = paths;
+        Ok(scanner)
+    }
+
+    pub fn from_settings(settings: &ScanSettings) -> Result<Self> {
+        let search_paths = if settings.roots.is_empty() {
+            Self::default_paths()
+        } else {
+            settings
+                .roots
+                .iter()
+                .map(|root| paths::expand(root))
+                .collect()
+        };
+
+        let mut exclude = GlobSetBuilder::new();
+        for pattern in &settings.exclude {
+            // Patterns naming a location (`~/.config/chromium`) get the same expansion as targets
+            let pattern = if pattern.starts_with('~') || pattern.starts_with('$') {
+                paths::expand(pattern).to_string_lossy().into_owned()
+            } else {
+                pattern.clone()
+            };
+            exclude.add(Glob::new(&pattern)?);
+        }
+
+        Ok(Self {
+            search_paths,
+            exclude: exclude.build()?,
+            max_depth: settings.max_depth,
+            follow_symlinks: settings.follow_symlinks,
+            use_cache: true,
+            warning: None,
+        })
+    }
+
+    pub fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+
+    /// Ignores the scan cache and walks every directory again.
+    pub fn rescan(mut self) -> Self {
+        self.use_cache = false;
+        self
+    }
+
+    fn default_paths() -> Vec<PathBuf> {
         let mut search_paths = vec![];
 
         // Add common dotfile locations
@@ -29,73 +135,184 @@ impl DotfileScanner {
             search_paths.push(current);
         }
 
-        Self { search_paths }
+        search_paths
     }
 
-    #[allow(dead_code)]
-    pub fn with_paths(paths: Vec<PathBuf>) -> Self {
-        Self { search_paths: paths }
+    /// The search paths that exist, as real paths. Roots often overlap (the
+    /// current directory is usually inside one), so scans work on real paths
+    /// and never walk the same directory twice.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.search_paths
+            .iter()
+            .filter_map(|path| fs::canonicalize(path).ok())
+            .collect()
     }
 
     pub fn scan(&self) -> Result<Vec<DotfileEntry>> {
-        let mut entries = vec![];
+        Ok(self.scan_report()?.entries)
+    }
 
-        for search_path in &self.search_paths {
-            if !search_path.exists() {
-                continue;
-            }
+    pub fn scan_report(&self) -> Result<ScanReport> {
+        let mut cache = ScanCache::load();
+        let previous = if self.use_cache {
+            &cache
+        } else {
+            &ScanCache::default()
+        };
+
+        let roots = self.roots();
+        let visited = Mutex::new(HashSet::new());
 
-            self.scan_directory(search_path, &mut entries)?;
+        let mut walk = Walk::default();
+        for root in &roots {
+            walk.append(self.scan_directory(root, 0, &[], previous, &visited));
         }
 
-        Ok(entries)
+        // A hardlinked or bind-mounted package can still resolve to one directory
+        let mut seen = HashSet::new();
+        walk.entries.retain(|entry| seen.insert(entry.path.clone()));
+
+        let dirs = walk.dirs.iter().map(|(dir, _)| dir.clone()).collect();
+        // The cache only speeds things up, so failing to write it is not an error
+        cache.update(&roots, walk.dirs);
+        let _ = cache.save();
+
+        Ok(ScanReport {
+            entries: walk.entries,
+            problems: walk.problems,
+            dirs,
+        })
     }
 
-    fn scan_directory(&self, dir: &Path, entries: &mut Vec<DotfileEntry>) -> Result<()> {
-        if !dir.is_dir() {
-            return Ok(());
+    /// Walks `dir` and, in parallel, every subdirectory worth visiting.
+    /// `ignores` holds the ignore rules of every ancestor, outermost first;
+    /// `visited` the (device, inode) of every directory walked so far.
+    fn scan_directory(
+        &self,
+        dir: &Path,
+        depth: usize,
+        ignores: &[Arc<Gitignore>],
+        cache: &ScanCache,
+        visited: &Mutex<HashSet<(u64, u64)>>,
+    ) -> Walk {
+        let mut walk = Walk::default();
+        let Ok(meta) = fs::metadata(dir) else {
+            return walk;
+        };
+        // Also stops symlink cycles, which lead back to a directory being walked
+        if !meta.is_dir() || !visited.lock().unwrap().insert((meta.dev(), meta.ino())) {
+            return walk;
         }
+        let Ok(mtime) = meta.modified() else {
+            return walk;
+        };
+
+        let cached = cache.dirs.get(dir);
+        let listing = match cached {
+            Some(cached) if cached.mtime == mtime => cached.refresh(),
+            _ => CachedDir::read(dir, mtime),
+        };
 
         // Look for dotwell.toml or dotwell.json
-        let toml_path = dir.join("dotwell.toml");
-        let json_path = dir.join("dotwell.json");
-
-        if toml_path.exists() {
-            if let Ok(config) = DotfileConfig::from_toml(&toml_path) {
-                entries.push(DotfileEntry {
-                    config,
-                    path: dir.to_path_buf(),
-                });
-            }
-        } else if json_path.exists() {
-            if let Ok(config) = DotfileConfig::from_json(&json_path) {
-                entries.push(DotfileEntry {
-                    config,
-                    path: dir.to_path_buf(),
-                });
-            }
+        match listing.manifest.as_ref().map(|manifest| &manifest.loaded) {
+            Some(Ok(config)) => walk.entries.push(DotfileEntry {
+                config: config.clone(),
+                // Reached through a followed symlink, `dir` is not the real path
+                path: fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()),
+            }),
+            Some(Err(problem)) => walk.problems.push(problem.clone()),
+            None => {}
+        }
+
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            walk.dirs.push((dir.to_path_buf(), listing));
+            return walk;
         }
 
+        let mut ignores = ignores.to_vec();
+        ignores.extend(ignore_rules(dir, &listing.ignore_files, &mut walk.problems).map(Arc::new));
+
+        // Skip hidden directories and common ignore patterns
+        let children: Vec<PathBuf> = listing
+            .subdirs
+            .iter()
+            .filter(|sub| self.follow_symlinks || !sub.symlink)
+            .filter(|sub| !is_skipped_dir(&sub.name))
+            .map(|sub| (sub, dir.join(&sub.name)))
+            .filter(|(sub, path)| !self.is_excluded(&sub.name, path) && !is_ignored(&ignores, path))
+            .map(|(_, path)| path)
+            .collect();
+        walk.dirs.push((dir.to_path_buf(), listing));
+
         // Recursively scan subdirectories
-        if let Ok(read_dir) = fs::read_dir(dir) {
-            for entry in read_dir.flatten() {
-                if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_dir() {
-                        let path = entry.path();
-                        // Skip hidden directories and common ignore patterns
-                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                            if !name.starts_with('.')
-                                && name != "target"
-                                && name != "node_modules"
-                                && name != "build" {
-                                self.scan_directory(&path, entries)?;
-                            }
-                        }
-                    }
-                }
-            }
+        let nested: Vec<Walk> = children
+            .par_iter()
+            .map(|child| self.scan_directory(child, depth + 1, &ignores, cache, visited))
+            .collect();
+        for child in nested {
+            walk.append(child);
+        }
+
+        walk
+    }
+
+    fn is_excluded(&self, name: &str, path: &Path) -> bool {
+        self.exclude.is_match(name) || self.exclude.is_match(path)
+    }
+}
+
+/// Hidden directories and the usual build output, never walked whatever
+/// the settings say.
+pub fn is_skipped_dir(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED_DIRS.contains(&name)
+}
+
+/// Rules from the ignore files in `dir`, if it has any. Unparseable lines are
+/// reported as problems and skipped.
+fn ignore_rules(
+    dir: &Path,
+    names: &[String],
+    problems: &mut Vec<ManifestProblem>,
+) -> Option<Gitignore> {
+    if names.is_empty() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in names {
+        let path = dir.join(name);
+        if let Some(error) = builder.add(&path) {
+            problems.push(ignore_problem(&path, error));
+        }
+    }
+
+    match builder.build() {
+        Ok(rules) => Some(rules),
+        Err(error) => {
+            problems.push(ManifestProblem::new(dir, error.to_string()));
+            None
         }
+    }
+}
 
-        Ok(())
+fn ignore_problem(path: &Path, error: ignore::Error) -> ManifestProblem {
+    match error {
+        ignore::Error::WithPath { err, .. } => ignore_problem(path, *err),
+        ignore::Error::WithLineNumber { line, err } => {
+            ManifestProblem::new(path, err.to_string()).at(line as usize, 1)
+        }
+        error => ManifestProblem::new(path, error.to_string()),
+    }
+}
+
+/// The innermost rule that matches decides, so a nested `!dir` can re-include.
+fn is_ignored(ignores: &[Arc<Gitignore>], path: &Path) -> bool {
+    for rules in ignores.iter().rev() {
+        match rules.matched(path, true) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
     }
+    false
 }
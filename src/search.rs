@@ -0,0 +1,123 @@
+use crate::config::{get_compiler_name, DotfileEntry};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::cmp::Reverse;
+
+/// What the browse list is narrowed down to.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub query: String,
+    pub category: Option<String>,
+    pub compiler: Option<&'static str>,
+}
+
+impl Filter {
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty() || self.category.is_some() || self.compiler.is_some()
+    }
+}
+
+/// An entry that passed the filter, with the characters the query matched.
+#[derive(Debug, Clone)]
+pub struct FilteredEntry {
+    /// Index into the full entry list.
+    pub index: usize,
+    pub score: i64,
+    pub name_hits: Vec<usize>,
+    pub description_hits: Vec<usize>,
+}
+
+/// Applies the chips, then fuzzy-matches the query against name, description,
+/// category and dependencies. Matches are ordered best first; without a
+/// query the original order is kept.
+pub fn filter_entries(entries: &[DotfileEntry], filter: &Filter) -> Vec<FilteredEntry> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let query = filter.query.trim();
+
+    let mut filtered: Vec<FilteredEntry> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| {
+            filter
+                .category
+                .as_ref()
+                .is_none_or(|category| &entry.config.category == category)
+                && filter
+                    .compiler
+                    .is_none_or(|compiler| get_compiler_name(&entry.config.compiler) == compiler)
+        })
+        .filter_map(|(index, entry)| {
+            if query.is_empty() {
+                return Some(FilteredEntry {
+                    index,
+                    score: 0,
+                    name_hits: vec![],
+                    description_hits: vec![],
+                });
+            }
+
+            let config = &entry.config;
+            let name = matcher.fuzzy_indices(&config.name, query);
+            let description = matcher.fuzzy_indices(&config.description, query);
+            let other = [
+                matcher.fuzzy_match(&config.category, query),
+                matcher.fuzzy_match(&config.dependencies.join(" "), query),
+            ];
+
+            let score = [
+                name.as_ref().map(|(score, _)| *score),
+                description.as_ref().map(|(score, _)| *score),
+            ]
+            .into_iter()
+            .chain(other)
+            .flatten()
+            .max()?;
+
+            Some(FilteredEntry {
+                index,
+                score,
+                name_hits: name.map(|(_, hits)| hits).unwrap_or_default(),
+                description_hits: description.map(|(_, hits)| hits).unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    if !query.is_empty() {
+        // Stable, so equal scores keep the scan order
+        filtered.sort_by_key(|entry| Reverse(entry.score));
+    }
+    filtered
+}
+
+/// Every category present, for the category chips.
+pub fn categories(entries: &[DotfileEntry]) -> Vec<String> {
+    let mut categories: Vec<String> = entries
+        .iter()
+        .map(|entry| entry.config.category.clone())
+        .collect();
+    categories.sort();
+    categories.dedup();
+    categories
+}
+
+/// Every compiler type present, for the compiler chips.
+pub fn compilers(entries: &[DotfileEntry]) -> Vec<&'static str> {
+    let mut compilers: Vec<&'static str> = entries
+        .iter()
+        .map(|entry| get_compiler_name(&entry.config.compiler))
+        .collect();
+    compilers.sort();
+    compilers.dedup();
+    compilers
+}
+
+/// The value after `current` in `options`, wrapping back to `None` (all).
+pub fn cycle<T: Clone + PartialEq>(options: &[T], current: Option<&T>) -> Option<T> {
+    match current {
+        None => options.first().cloned(),
+        Some(current) => {
+            let position = options.iter().position(|option| option == current)?;
+            options.get(position + 1).cloned()
+        }
+    }
+}
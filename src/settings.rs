@@ -0,0 +1,86 @@
+use crate::config::ManifestProblem;
+use crate::graphics::Protocol;
+use crate::paths;
+use crate::profile::ProfileConfig;
+use color_eyre::Result;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// Global preferences from `$XDG_CONFIG_HOME/dotwell/config.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub scan: ScanSettings,
+    pub browse: BrowseSettings,
+    pub preview: PreviewSettings,
+    /// Named sets of entries, applied with `dwell apply <profile>`.
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Directories to search; the built-in locations are used when empty.
+    pub roots: Vec<String>,
+    /// Directory names or globs to skip, on top of the built-in skip list.
+    pub exclude: Vec<String>,
+    /// How many levels below a root to descend; unlimited when unset.
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BrowseSettings {
+    /// Give packages that share a parent directory their own node in the tree.
+    pub group_by_directory: bool,
+    /// Icon per category; `default` covers categories without one.
+    pub icons: HashMap<String, String>,
+}
+
+impl Default for BrowseSettings {
+    fn default() -> Self {
+        Self {
+            group_by_directory: true,
+            icons: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PreviewSettings {
+    /// How screenshots are drawn; detected from the terminal by default.
+    pub graphics: Protocol,
+}
+
+impl BrowseSettings {
+    pub fn icon(&self, category: &str) -> &str {
+        if let Some(icon) = self.icons.get(category) {
+            return icon;
+        }
+        match category {
+            "themes" => "🎨",
+            _ => self.icons.get("default").map_or("📦", |icon| icon.as_str()),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        paths::config_home().join("dotwell").join("config.toml")
+    }
+
+    /// Loads the settings file, or the defaults when there is none.
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let settings = toml::from_str(&content)
+            .map_err(|e| ManifestProblem::from_toml_error(&path, &content, &e))?;
+        Ok(settings)
+    }
+}
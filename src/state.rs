@@ -0,0 +1,141 @@
+use crate::backup;
+use crate::config::DotfileEntry;
+use crate::paths;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Everything Dotwell remembers about past installs, keyed by entry path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallState {
+    pub entries: BTreeMap<String, EntryState>,
+    /// Profile applied last, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryState {
+    pub name: String,
+    pub category: String,
+    pub path: PathBuf,
+    pub installed_at: String,
+    /// Nanoseconds since the Unix epoch. Orders installs within the same
+    /// second, e.g. by a batch install; zero in older state files.
+    #[serde(default)]
+    pub installed_ns: u128,
+    /// Git commit of the dotfile directory, or a checksum of its declared
+    /// files outside a repo.
+    pub source: String,
+    pub files: Vec<DeployedFile>,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployedFile {
+    pub file: String,
+    pub dest: PathBuf,
+    pub sha256: String,
+}
+
+impl InstallState {
+    pub fn path() -> PathBuf {
+        paths::dotwell_state_dir().join("state.json")
+    }
+
+    pub fn load() -> Result<Self> {
+        match fs::read_to_string(Self::path()) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, entry: &DotfileEntry) -> Option<&EntryState> {
+        self.entries.get(&key(&entry.path))
+    }
+
+    /// Records the outcome of installing `entry`, checksumming each deployed target.
+    pub fn record_install(&mut self, entry: &DotfileEntry, success: bool) {
+        let files = entry
+            .deploy_targets()
+            .into_iter()
+            .filter_map(|target| {
+                Some(DeployedFile {
+                    sha256: backup::checksum(&target.dest).ok()?,
+                    file: target.file,
+                    dest: target.dest,
+                })
+            })
+            .collect();
+
+        self.entries.insert(
+            key(&entry.path),
+            EntryState {
+                name: entry.config.name.clone(),
+                category: entry.config.category.clone(),
+                path: entry.path.clone(),
+                installed_at: backup::timestamp(),
+                installed_ns: backup::now_ns(),
+                source: source_revision(entry),
+                files,
+                success,
+            },
+        );
+    }
+
+    /// Forgets the install of the entry at `path`, e.g. after rolling it back.
+    pub fn forget(&mut self, path: &Path) {
+        self.entries.remove(&key(path));
+    }
+
+    /// Whether `entry` is the most recent successful install in its category.
+    pub fn is_active(&self, entry: &DotfileEntry) -> bool {
+        let active = self
+            .entries
+            .values()
+            .filter(|state| state.success && state.category == entry.config.category)
+            .max_by(|a, b| a.recency().cmp(&b.recency()));
+
+        active.is_some_and(|state| state.path == entry.path)
+    }
+}
+
+impl EntryState {
+    /// Sort key for when the install happened, latest last.
+    pub fn recency(&self) -> (u128, &str) {
+        (self.installed_ns, &self.installed_at)
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn source_revision(entry: &DotfileEntry) -> String {
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&entry.path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+    match commit {
+        Some(commit) => format!("git:{}", commit),
+        None => backup::checksum_files(&entry.path, &entry.config.files)
+            .map(|sum| format!("sha256:{}", sum))
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,172 @@
+use crate::backup::checksum;
+use crate::config::DotfileEntry;
+use crate::state::InstallState;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a deployed file (or a whole entry) compares to what Dotwell installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    NotInstalled,
+    Clean,
+    /// The file in the dotfile directory changed since install.
+    SourceChanged,
+    /// The deployed file was edited outside Dotwell.
+    Modified,
+    Missing,
+}
+
+#[derive(Serialize)]
+pub struct FileReport {
+    pub file: String,
+    pub dest: PathBuf,
+    pub status: Status,
+}
+
+pub struct EntryReport {
+    pub status: Status,
+    pub files: Vec<FileReport>,
+}
+
+/// Compares each deployed target against its recorded checksum and against
+/// the current source. A symlinked target shows the source's content, so it
+/// only counts as modified when it points somewhere else. The entry takes
+/// the worst status of its files.
+pub fn entry_status(entry: &DotfileEntry, state: &InstallState) -> EntryReport {
+    let Some(installed) = state.get(entry) else {
+        return EntryReport {
+            status: Status::NotInstalled,
+            files: vec![],
+        };
+    };
+
+    let files: Vec<FileReport> = installed
+        .files
+        .iter()
+        .map(|deployed| {
+            let source = entry.path.join(&deployed.file);
+            let source_status = || match checksum(&source) {
+                Ok(sum) if sum == deployed.sha256 => Status::Clean,
+                _ => Status::SourceChanged,
+            };
+            let status = match fs::read_link(&deployed.dest) {
+                Ok(link) if links_to(&link, &source) => source_status(),
+                Ok(_) => Status::Modified,
+                Err(_) => match checksum(&deployed.dest) {
+                    Err(_) => Status::Missing,
+                    Ok(sum) if sum != deployed.sha256 => Status::Modified,
+                    Ok(_) => source_status(),
+                },
+            };
+            FileReport {
+                file: deployed.file.clone(),
+                dest: deployed.dest.clone(),
+                status,
+            }
+        })
+        .collect();
+
+    EntryReport {
+        status: files
+            .iter()
+            .map(|f| f.status)
+            .max()
+            .unwrap_or(Status::Clean),
+        files,
+    }
+}
+
+fn links_to(link: &Path, source: &Path) -> bool {
+    link == source || source.canonicalize().is_ok_and(|source| source == link)
+}
+
+pub fn get_status_name(status: Status) -> &'static str {
+    match status {
+        Status::NotInstalled => "not installed",
+        Status::Clean => "clean",
+        Status::SourceChanged => "source changed",
+        Status::Modified => "modified",
+        Status::Missing => "missing",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DotfileConfig;
+    use std::os::unix::fs::symlink;
+
+    /// An entry deploying `theme.conf` from a scratch directory to a
+    /// `deployed.conf` next to it.
+    fn entry(test: &str) -> (DotfileEntry, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("dwell-status-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("theme.conf"), "background #1d1f21\n").unwrap();
+
+        let dest = dir.join("deployed.conf");
+        let manifest = format!(
+            "name = \"theme\"\nfiles = [\"theme.conf\"]\n[targets]\n\"theme.conf\" = {:?}",
+            dest.to_string_lossy()
+        );
+        let config: DotfileConfig = toml::from_str(&manifest).unwrap();
+        (DotfileEntry { config, path: dir }, dest)
+    }
+
+    fn status(entry: &DotfileEntry, state: &InstallState) -> Status {
+        entry_status(entry, state).status
+    }
+
+    #[test]
+    fn not_installed_without_state() {
+        let (entry, _) = entry("not-installed");
+        assert_eq!(
+            status(&entry, &InstallState::default()),
+            Status::NotInstalled
+        );
+        fs::remove_dir_all(&entry.path).unwrap();
+    }
+
+    #[test]
+    fn copied_file_drifts() {
+        let (entry, dest) = entry("copied");
+        fs::copy(entry.path.join("theme.conf"), &dest).unwrap();
+        let mut state = InstallState::default();
+        state.record_install(&entry, true);
+        assert_eq!(status(&entry, &state), Status::Clean);
+
+        fs::write(entry.path.join("theme.conf"), "background #000000\n").unwrap();
+        assert_eq!(status(&entry, &state), Status::SourceChanged);
+
+        fs::write(&dest, "edited by hand\n").unwrap();
+        assert_eq!(status(&entry, &state), Status::Modified);
+
+        fs::remove_file(&dest).unwrap();
+        assert_eq!(status(&entry, &state), Status::Missing);
+        fs::remove_dir_all(&entry.path).unwrap();
+    }
+
+    #[test]
+    fn linked_file_follows_its_source() {
+        let (entry, dest) = entry("linked");
+        symlink(entry.path.join("theme.conf"), &dest).unwrap();
+        let mut state = InstallState::default();
+        state.record_install(&entry, true);
+        assert_eq!(status(&entry, &state), Status::Clean);
+
+        // Editing through the link edits the source, which is not drift
+        fs::write(&dest, "background #000000\n").unwrap();
+        assert_eq!(status(&entry, &state), Status::SourceChanged);
+
+        fs::remove_file(&dest).unwrap();
+        symlink(entry.path.join("elsewhere.conf"), &dest).unwrap();
+        assert_eq!(status(&entry, &state), Status::Modified);
+
+        fs::remove_file(&dest).unwrap();
+        assert_eq!(status(&entry, &state), Status::Missing);
+        fs::remove_dir_all(&entry.path).unwrap();
+    }
+}
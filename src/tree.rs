@@ -0,0 +1,128 @@
+use crate::config::DotfileEntry;
+use crate::search::FilteredEntry;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// One line of the browse tree.
+#[derive(Debug, Clone)]
+pub enum BrowseRow {
+    /// A category, or packages sharing a parent directory inside one.
+    Group {
+        key: String,
+        label: String,
+        category: String,
+        depth: usize,
+        count: usize,
+        collapsed: bool,
+    },
+    Entry {
+        /// Index into the full entry list.
+        index: usize,
+        /// Index into the filtered list, for match highlights.
+        filtered: usize,
+        depth: usize,
+    },
+}
+
+/// Identifies a row across rebuilds, so the cursor can stay put.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowKey {
+    Group(String),
+    Entry(PathBuf),
+}
+
+impl BrowseRow {
+    pub fn key(&self, entries: &[DotfileEntry]) -> RowKey {
+        match self {
+            BrowseRow::Group { key, .. } => RowKey::Group(key.clone()),
+            BrowseRow::Entry { index, .. } => RowKey::Entry(entries[*index].path.clone()),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            BrowseRow::Group { depth, .. } | BrowseRow::Entry { depth, .. } => *depth,
+        }
+    }
+}
+
+/// Groups the filtered entries by category, then (with `by_directory`) puts
+/// variants sharing a parent directory, like `htop-themes/{blue,red}`, under
+/// their own node. Search roots never become a node. Everything is expanded
+/// when `expand_all` is set, e.g. while a search is active.
+pub fn build_rows(
+    entries: &[DotfileEntry],
+    filtered: &[FilteredEntry],
+    collapsed: &HashSet<String>,
+    roots: &[PathBuf],
+    by_directory: bool,
+    expand_all: bool,
+) -> Vec<BrowseRow> {
+    let mut categories: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (position, matched) in filtered.iter().enumerate() {
+        let category = entries[matched.index].config.category.as_str();
+        categories.entry(category).or_default().push(position);
+    }
+
+    let is_collapsed = |key: &str| !expand_all && collapsed.contains(key);
+    let entry_row = |position: usize, depth: usize| BrowseRow::Entry {
+        index: filtered[position].index,
+        filtered: position,
+        depth,
+    };
+
+    let mut rows = vec![];
+    for (category, positions) in categories {
+        rows.push(BrowseRow::Group {
+            key: category.to_string(),
+            label: category.to_string(),
+            category: category.to_string(),
+            depth: 0,
+            count: positions.len(),
+            collapsed: is_collapsed(category),
+        });
+        if is_collapsed(category) {
+            continue;
+        }
+        if !by_directory {
+            rows.extend(positions.into_iter().map(|position| entry_row(position, 1)));
+            continue;
+        }
+
+        // Parents in order of first appearance
+        let mut parents: Vec<(&Path, Vec<usize>)> = vec![];
+        for position in positions {
+            let path = &entries[filtered[position].index].path;
+            let parent = path.parent().unwrap_or(path);
+            match parents.iter_mut().find(|(p, _)| *p == parent) {
+                Some((_, siblings)) => siblings.push(position),
+                None => parents.push((parent, vec![position])),
+            }
+        }
+
+        for (parent, positions) in parents {
+            if positions.len() < 2 || roots.iter().any(|root| root == parent) {
+                rows.extend(positions.into_iter().map(|position| entry_row(position, 1)));
+                continue;
+            }
+
+            let key = format!("{}/{}", category, parent.display());
+            let collapsed = is_collapsed(&key);
+            rows.push(BrowseRow::Group {
+                label: parent.file_name().map_or_else(
+                    || parent.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                ),
+                category: category.to_string(),
+                depth: 1,
+                count: positions.len(),
+                collapsed,
+                key,
+            });
+            if !collapsed {
+                rows.extend(positions.into_iter().map(|position| entry_row(position, 2)));
+            }
+        }
+    }
+    rows
+}
@@ -1,20 +1,39 @@
 use crate::app::{App, View};
-use crate::config::get_compiler_name;
+use crate::backup::BackupKind;
+use crate::config::{get_compiler_name, get_deploy_name};
+use crate::deps::{DepCheck, DepStatus};
+use crate::diff::DiffLine;
+use crate::graphics::Protocol;
+use crate::highlight::TokenKind;
+use crate::installer::{DeployAction, EntryOutcome, InstallPlan};
+use crate::palette::Swatch;
+use crate::profile::ProfileAction;
+use crate::search;
+use crate::status::Status;
+use crate::tree::BrowseRow;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-pub fn render(frame: &mut Frame, app: &App) {
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Draws the current view. Returns where a screenshot should be drawn
+/// when the terminal shows it through a graphics protocol.
+pub fn render(frame: &mut Frame, app: &App) -> Option<Rect> {
     match app.view {
         View::Home => render_home(frame, app),
         View::Browse => render_browse(frame, app),
-        View::Preview => render_preview(frame, app),
+        View::Preview => return render_preview(frame, app),
+        View::Diff => render_diff(frame, app),
         View::Installing => render_installing(frame, app),
+        View::Problems => render_problems(frame, app),
+        View::Profiles => render_profiles(frame, app),
     }
+    None
 }
 
 fn render_home(frame: &mut Frame, app: &App) {
@@ -37,19 +56,33 @@ fn render_home(frame: &mut Frame, app: &App) {
         if app.dotfiles.len() == 1 { "" } else { "s" }
     );
 
-    let content = vec![
-        Line::from(""),
-        Line::from(stats).centered(),
+    let mut content = vec![Line::from(""), Line::from(stats).centered()];
+    if !app.problems.is_empty() {
+        content.push(
+            Line::from(Span::styled(
+                format!(
+                    "{} problem{} with manifests",
+                    app.problems.len(),
+                    if app.problems.len() == 1 { "" } else { "s" }
+                ),
+                Style::default().fg(Color::Red),
+            ))
+            .centered(),
+        );
+    }
+    content.extend([
         Line::from("").centered(),
         Line::from("Press 'b' to browse dotfiles").centered(),
+        Line::from("Press 'p' to list problems").centered(),
+        Line::from("Press 'P' to apply a profile").centered(),
         Line::from("Press 'q' to quit").centered(),
-    ];
+    ]);
 
     let content_widget = Paragraph::new(content)
         .block(Block::default().borders(Borders::ALL).title(" Welcome "));
 
     frame.render_widget(content_widget, chunks[1]);
-    render_footer(frame, chunks[2], "Home");
+    render_footer(frame, chunks[2], app, "Home");
 }
 
 fn render_browse(frame: &mut Frame, app: &App) {
@@ -58,80 +91,324 @@ fn render_browse(frame: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
+            Constraint::Length(1), // Search and filter chips
             Constraint::Min(0),    // Main content
             Constraint::Length(3), // Footer
         ])
         .split(area);
 
+    render_filter_bar(frame, chunks[0], app);
+
     if app.dotfiles.is_empty() {
         let msg = Paragraph::new("No dotfiles found.\n\nCreate a dotwell.toml or dotwell.json file in your dotfiles directory.")
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL).title(" Browse Dotfiles "));
-        frame.render_widget(msg, chunks[0]);
+        frame.render_widget(msg, chunks[1]);
+    } else if app.filtered.is_empty() {
+        let msg = Paragraph::new("No matches.\n\nEsc clears the search and filters.")
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Browse Dotfiles "),
+            );
+        frame.render_widget(msg, chunks[1]);
     } else {
         let items: Vec<ListItem> = app
-            .dotfiles
+            .rows
             .iter()
             .enumerate()
-            .map(|(i, entry)| {
-                let compiler = get_compiler_name(&entry.config.compiler);
-
-                // Add indent for theme category items
-                let indent = if entry.config.category == "themes" {
-                    "    "
+            .map(|(row, item)| {
+                let style = if row == app.cursor {
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD)
                 } else {
-                    "  "
+                    Style::default()
                 };
 
-                let icon = if entry.config.category == "themes" {
-                    "🎨 "
-                } else {
-                    "📦 "
+                let (index, matched, depth) = match item {
+                    BrowseRow::Group {
+                        label,
+                        category,
+                        depth,
+                        count,
+                        collapsed,
+                        ..
+                    } => {
+                        let icon = if *depth == 0 {
+                            app.browse.icon(category)
+                        } else {
+                            "📁"
+                        };
+                        let content = Line::from(vec![
+                            Span::raw("  ".repeat(*depth + 1)),
+                            Span::styled(
+                                if *collapsed { "▸ " } else { "▾ " },
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                            Span::raw(format!("{} ", icon)),
+                            Span::styled(label.clone(), Style::default().fg(Color::Magenta).bold()),
+                            Span::styled(
+                                format!(" ({})", count),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        ]);
+                        return ListItem::new(content).style(style);
+                    }
+                    BrowseRow::Entry {
+                        index,
+                        filtered,
+                        depth,
+                    } => (*index, &app.filtered[*filtered], *depth),
                 };
+                let entry = &app.dotfiles[index];
+                let compiler = get_compiler_name(&entry.config.compiler);
+                let indent = "  ".repeat(depth + 1);
 
-                let content = Line::from(vec![
-                    Span::raw(indent),
-                    Span::styled(icon, Style::default()),
-                    Span::styled(
-                        format!("{} ", entry.config.name),
-                        Style::default().fg(Color::Cyan).bold(),
-                    ),
+                let mut spans = vec![Span::raw(indent)];
+                spans.push(if app.marked.contains(&entry.path) {
+                    Span::styled("◆ ", Style::default().fg(Color::Magenta).bold())
+                } else {
+                    Span::raw("  ")
+                });
+                spans.extend(highlighted(
+                    &entry.config.name,
+                    &matched.name_hits,
+                    Style::default().fg(Color::Cyan).bold(),
+                ));
+                spans.extend([
+                    Span::raw(" "),
                     Span::styled(
                         format!("[{}] ", compiler),
                         Style::default().fg(Color::Yellow),
                     ),
-                    Span::styled(
-                        format!("{}", entry.config.description),
-                        Style::default().fg(Color::Gray),
-                    ),
                 ]);
 
-                let style = if i == app.selected_index {
-                    Style::default()
-                        .bg(Color::DarkGray)
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default()
-                };
+                if app.state.is_active(entry) {
+                    spans.push(Span::styled("● active ", Style::default().fg(Color::Green)));
+                }
+                if let Some(badge) = app.statuses.get(&entry.path).and_then(|s| status_badge(*s)) {
+                    spans.push(badge);
+                }
 
-                ListItem::new(content).style(style)
+                spans.extend(highlighted(
+                    &entry.config.description,
+                    &matched.description_hits,
+                    Style::default().fg(Color::Gray),
+                ));
+                ListItem::new(Line::from(spans)).style(style)
             })
             .collect();
 
+        let marked = if app.marked.is_empty() {
+            String::new()
+        } else {
+            format!("· {} marked ", app.marked.len())
+        };
         let list = List::new(items).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Browse Dotfiles ")
-                .title_bottom(" ↑/↓: navigate | Enter: preview | i: install | Esc/b: back | q: quit "),
+                .title(format!(
+                    " Browse Dotfiles ({}/{}) {}",
+                    app.filtered.len(),
+                    app.dotfiles.len(),
+                    marked
+                ))
+                .title_bottom(" ↑/↓: navigate | ←/→: fold | /: search | c/t: filter | Enter: preview | i: install | I: force install | space: mark | M: install marked | C: keep going | P: profiles | D: dry run | u: rollback | Esc/b: back | q: quit "),
         );
 
-        frame.render_widget(list, chunks[0]);
+        // The state only scrolls the list; the selected row is styled above
+        let mut state = ListState::default().with_selected(Some(app.cursor));
+        frame.render_stateful_widget(list, chunks[1], &mut state);
+    }
+
+    if app.pending_rollback.is_some() {
+        render_rollback_dialog(frame, app);
     }
 
-    render_footer(frame, chunks[1], "Browse");
+    render_footer(frame, chunks[2], app, "Browse");
 }
 
-fn render_preview(frame: &mut Frame, app: &App) {
+/// `text` split into spans, with the characters at `hits` emphasized.
+fn highlighted(text: &str, hits: &[usize], style: Style) -> Vec<Span<'static>> {
+    let hit_style = style.fg(Color::Magenta).underlined();
+    let mut spans: Vec<Span> = vec![];
+    let mut run = String::new();
+    let mut run_is_hit = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let is_hit = hits.contains(&i);
+        if is_hit != run_is_hit && !run.is_empty() {
+            let style = if run_is_hit { hit_style } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_is_hit = is_hit;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(
+            run,
+            if run_is_hit { hit_style } else { style },
+        ));
+    }
+    spans
+}
+
+/// One line above the list: the search query and the category/compiler chips.
+fn render_filter_bar(frame: &mut Frame, area: Rect, app: &App) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut spans = vec![Span::styled(" / ", Style::default().fg(Color::Yellow))];
+
+    if app.searching || !app.filter.query.is_empty() {
+        spans.push(Span::raw(app.filter.query.clone()));
+        if app.searching {
+            spans.push(Span::styled("▏", Style::default().fg(Color::Yellow)));
+        }
+    } else {
+        spans.push(Span::styled("search", dim));
+    }
+
+    let chip = |label: String, active: bool| {
+        if active {
+            Span::styled(
+                format!(" {} ", label),
+                Style::default().fg(Color::Black).bg(Color::Cyan),
+            )
+        } else {
+            Span::styled(format!(" {} ", label), dim)
+        }
+    };
+
+    spans.push(Span::styled("   c ", Style::default().fg(Color::Yellow)));
+    spans.push(chip("all".to_string(), app.filter.category.is_none()));
+    for category in search::categories(&app.dotfiles) {
+        let active = app.filter.category.as_ref() == Some(&category);
+        spans.push(chip(category, active));
+    }
+
+    spans.push(Span::styled("   t ", Style::default().fg(Color::Yellow)));
+    spans.push(chip("all".to_string(), app.filter.compiler.is_none()));
+    for compiler in search::compilers(&app.dotfiles) {
+        spans.push(chip(
+            compiler.to_string(),
+            app.filter.compiler == Some(compiler),
+        ));
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn dependency_line(dep: &DepCheck) -> Line<'static> {
+    let (icon, color, note) = match &dep.status {
+        DepStatus::Installed { version, .. } => (
+            "✓",
+            Color::Green,
+            version
+                .clone()
+                .map(|v| format!(" ({})", v))
+                .unwrap_or_default(),
+        ),
+        DepStatus::WrongVersion { found, .. } => (
+            "⚠",
+            Color::Yellow,
+            format!(" (found {})", found.as_deref().unwrap_or("unknown version")),
+        ),
+        DepStatus::Unchecked { .. } => (
+            "?",
+            Color::DarkGray,
+            " (version not checked yet)".to_string(),
+        ),
+        DepStatus::Missing => ("✗", Color::Red, " (missing)".to_string()),
+    };
+
+    Line::from(vec![
+        Span::styled(format!("  {} ", icon), Style::default().fg(color).bold()),
+        Span::raw(dep.spec.clone()),
+        Span::styled(note, Style::default().fg(Color::DarkGray)),
+    ])
+}
+
+fn status_badge(status: Status) -> Option<Span<'static>> {
+    let (text, color) = match status {
+        Status::NotInstalled => return None,
+        Status::Clean => ("✓ clean ", Color::Green),
+        Status::SourceChanged => ("↻ source changed ", Color::Cyan),
+        Status::Modified => ("✎ modified ", Color::Yellow),
+        Status::Missing => ("✗ missing ", Color::Red),
+    };
+    Some(Span::styled(text, Style::default().fg(color)))
+}
+
+fn render_rollback_dialog(frame: &mut Frame, app: &App) {
+    let Some(snapshot) = &app.pending_rollback else {
+        return;
+    };
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Entry: ", Style::default().fg(Color::Yellow).bold()),
+            Span::raw(&snapshot.manifest.entry),
+        ]),
+        Line::from(vec![
+            Span::styled("Snapshot: ", Style::default().fg(Color::Yellow).bold()),
+            Span::raw(&snapshot.manifest.timestamp),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Will restore:",
+            Style::default().fg(Color::Yellow).bold(),
+        )),
+    ];
+
+    for file in &snapshot.manifest.files {
+        let action = match file.kind {
+            BackupKind::Missing => "remove ",
+            BackupKind::Symlink { .. } => "relink ",
+            BackupKind::File | BackupKind::Directory => "restore",
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} ", action), Style::default().fg(Color::Cyan)),
+            Span::raw(file.original.display().to_string()),
+        ]));
+    }
+
+    let area = centered_rect(70, 50, frame.area());
+    let dialog = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Roll back? ")
+                .title_bottom(" y/Enter: confirm | n/Esc: cancel "),
+        )
+        .wrap(Wrap { trim: false });
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn render_preview(frame: &mut Frame, app: &App) -> Option<Rect> {
     let area = frame.area();
 
     let chunks = Layout::default()
@@ -142,6 +419,7 @@ fn render_preview(frame: &mut Frame, app: &App) {
         ])
         .split(area);
 
+    let mut image_area = None;
     if let Some(entry) = app.selected_dotfile() {
         let main_chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -186,8 +464,59 @@ fn render_preview(frame: &mut Frame, app: &App) {
         ];
 
         let mut metadata_lines = metadata;
-        for dep in &entry.config.dependencies {
-            metadata_lines.push(Line::from(format!("  • {}", dep)));
+        for dep in &app.dep_checks {
+            metadata_lines.push(dependency_line(dep));
+        }
+        if app.is_checking_dependencies() {
+            metadata_lines.push(Line::from(Span::styled(
+                "  Checking versions...",
+                Style::default().fg(Color::DarkGray),
+            )));
+        } else if app.dep_checks.iter().any(|dep| !dep.is_satisfied()) {
+            metadata_lines.push(Line::from(Span::styled(
+                "  Install is blocked; press 'I' to install anyway",
+                Style::default().fg(Color::Red),
+            )));
+        }
+        if !entry.config.requires.is_empty() {
+            metadata_lines.extend([
+                Line::from(""),
+                Line::from(Span::styled(
+                    "Requires:",
+                    Style::default().fg(Color::Yellow).bold(),
+                )),
+                Line::from(format!("  {}", entry.config.requires.join(", "))),
+            ]);
+        }
+
+        if let Some(installed) = app.state.get(entry) {
+            metadata_lines.push(Line::from(""));
+            metadata_lines.push(Line::from(vec![
+                Span::styled("Installed: ", Style::default().fg(Color::Yellow).bold()),
+                Span::raw(&installed.installed_at),
+                Span::styled(
+                    if installed.success {
+                        "  ✓"
+                    } else {
+                        "  ✗ failed"
+                    },
+                    Style::default().fg(if installed.success {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    }),
+                ),
+            ]));
+            metadata_lines.push(Line::from(vec![
+                Span::styled("Source: ", Style::default().fg(Color::Yellow).bold()),
+                Span::styled(&installed.source, Style::default().fg(Color::Gray)),
+            ]));
+            if let Some(badge) = app.statuses.get(&entry.path).and_then(|s| status_badge(*s)) {
+                metadata_lines.push(Line::from(vec![
+                    Span::styled("Status: ", Style::default().fg(Color::Yellow).bold()),
+                    badge,
+                ]));
+            }
         }
 
         let metadata_widget = Paragraph::new(metadata_lines)
@@ -195,29 +524,447 @@ fn render_preview(frame: &mut Frame, app: &App) {
                 Block::default()
                     .borders(Borders::ALL)
                     .title(" Metadata ")
-                    .title_bottom(" Press 'i' or Enter to install "),
+                    .title_bottom(" i/Enter: install | d: diff "),
             )
             .wrap(Wrap { trim: true });
 
-        frame.render_widget(metadata_widget, main_chunks[0]);
+        let left_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(palette_height(&app.palette)),
+            ])
+            .split(main_chunks[0]);
 
-        // Right panel: files
-        let mut files_lines = vec![Line::from("")];
-        for file in &entry.config.files {
-            files_lines.push(Line::from(vec![
-                Span::raw("  "),
-                Span::styled("📄 ", Style::default().fg(Color::Blue)),
-                Span::raw(file),
-            ]));
+        frame.render_widget(metadata_widget, left_chunks[0]);
+        render_palette(frame, left_chunks[1], &app.palette);
+
+        // Right panel: screenshot, then files above the selected file's contents
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(if app.screenshot.is_some() { 50 } else { 0 }),
+                Constraint::Length(
+                    (entry.config.files.len() as u16 + 2).min(main_chunks[1].height / 3),
+                ),
+                Constraint::Min(0),
+            ])
+            .split(main_chunks[1]);
+
+        let file_items: Vec<ListItem> = entry
+            .config
+            .files
+            .iter()
+            .map(|file| {
+                let mut spans = vec![
+                    Span::styled("📄 ", Style::default().fg(Color::Blue)),
+                    Span::raw(file),
+                ];
+                if let Some(target) = entry.config.targets.get(file) {
+                    spans.push(Span::styled(
+                        format!("  → {}", target),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let files_title = if entry.config.targets.is_empty() {
+            " Files ".to_string()
+        } else {
+            format!(" Files ({}) ", get_deploy_name(entry.config.deploy))
+        };
+        let files_widget = List::new(file_items)
+            .block(Block::default().borders(Borders::ALL).title(files_title))
+            .highlight_style(Style::default().bg(Color::DarkGray).bold())
+            .highlight_symbol("▶ ");
+
+        let mut files_state = ListState::default();
+        files_state.select(Some(app.preview_file));
+        frame.render_stateful_widget(files_widget, right_chunks[1], &mut files_state);
+
+        render_file_view(frame, right_chunks[2], app);
+        image_area = render_screenshot(frame, right_chunks[0], app);
+    }
+
+    render_footer(frame, chunks[1], app, "Preview");
+    image_area
+}
+
+/// Swatches per row: a terminal palette shows as normal and bright rows.
+const SWATCHES_PER_ROW: usize = 8;
+
+fn palette_height(palette: &[Swatch]) -> u16 {
+    if palette.is_empty() {
+        0
+    } else {
+        (palette.len().div_ceil(SWATCHES_PER_ROW) * 2 + 2) as u16
+    }
+}
+
+/// Each swatch is a two-row block of its true color.
+fn render_palette(frame: &mut Frame, area: Rect, palette: &[Swatch]) {
+    if palette.is_empty() {
+        return;
+    }
+
+    let mut lines = vec![];
+    for row in palette.chunks(SWATCHES_PER_ROW) {
+        let blocks: Vec<Span> = row
+            .iter()
+            .flat_map(|swatch| {
+                let (r, g, b) = swatch.rgb;
+                [
+                    Span::styled("    ", Style::default().bg(Color::Rgb(r, g, b))),
+                    Span::raw(" "),
+                ]
+            })
+            .collect();
+        lines.push(Line::from(blocks.clone()));
+        lines.push(Line::from(blocks));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Palette ({}) ", palette.len()));
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Draws the screenshot as half blocks, or leaves its area blank and
+/// returns it for the graphics protocol.
+fn render_screenshot(frame: &mut Frame, area: Rect, app: &App) -> Option<Rect> {
+    let screenshot = match app.screenshot.as_ref()? {
+        Ok(screenshot) => screenshot,
+        Err(e) => {
+            let error = Paragraph::new(Span::styled(e.as_str(), Style::default().fg(Color::Red)))
+                .block(Block::default().borders(Borders::ALL).title(" Screenshot "))
+                .wrap(Wrap { trim: true });
+            frame.render_widget(error, area);
+            return None;
         }
+    };
+
+    let block = Block::default().borders(Borders::ALL).title(" Screenshot ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    if inner.is_empty() {
+        return None;
+    }
+    if app.graphics != Protocol::Halfblocks {
+        return Some(inner);
+    }
+
+    let fitted = screenshot.fit(inner);
+    let lines: Vec<Line> = screenshot
+        .half_blocks(fitted.width, fitted.height)
+        .into_iter()
+        .map(|row| {
+            Line::from(
+                row.into_iter()
+                    .map(|[(r, g, b), (br, bg, bb)]| {
+                        Span::styled(
+                            "▀",
+                            Style::default()
+                                .fg(Color::Rgb(r, g, b))
+                                .bg(Color::Rgb(br, bg, bb)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), fitted);
+    None
+}
+
+fn render_file_view(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(view) = &app.file_view else {
+        frame.render_widget(
+            Block::default().borders(Borders::ALL).title(" Contents "),
+            area,
+        );
+        return;
+    };
+
+    let mut lines: Vec<Line> = view
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(number, tokens)| {
+            let mut spans = vec![Span::styled(
+                format!("{:>4} ", number + 1),
+                Style::default().fg(Color::DarkGray),
+            )];
+            spans.extend(
+                tokens
+                    .iter()
+                    .map(|(kind, text)| Span::styled(text.as_str(), token_style(*kind))),
+            );
+            Line::from(spans)
+        })
+        .collect();
+    if let Some(note) = &view.note {
+        lines.push(Line::from(Span::styled(
+            note.as_str(),
+            Style::default().fg(Color::DarkGray).italic(),
+        )));
+    }
+
+    let content = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Contents ({}) ", view.language.name()))
+                .title_bottom(" ↑/↓: file | J/K, PgUp/PgDn: scroll "),
+        )
+        .scroll((app.file_scroll, 0));
 
-        let files_widget = Paragraph::new(files_lines)
-            .block(Block::default().borders(Borders::ALL).title(" Files "));
+    frame.render_widget(content, area);
+}
 
-        frame.render_widget(files_widget, main_chunks[1]);
+fn token_style(kind: TokenKind) -> Style {
+    match kind {
+        TokenKind::Text => Style::default(),
+        TokenKind::Comment => Style::default().fg(Color::DarkGray).italic(),
+        TokenKind::String => Style::default().fg(Color::Green),
+        TokenKind::Number => Style::default().fg(Color::Magenta),
+        TokenKind::Keyword => Style::default().fg(Color::Red).bold(),
+        TokenKind::Key => Style::default().fg(Color::Cyan),
+        TokenKind::Section => Style::default().fg(Color::Yellow).bold(),
+        TokenKind::Punctuation => Style::default().fg(Color::Gray),
     }
+}
+
+fn render_diff(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Main content
+            Constraint::Length(3), // Footer
+        ])
+        .split(area);
+
+    let lines: Vec<Line> = app
+        .diff_lines
+        .iter()
+        .map(|line| match line {
+            DiffLine::File(text) => {
+                Line::from(Span::styled(text.as_str(), Style::default().bold()))
+            }
+            DiffLine::Hunk(text) => Line::from(Span::styled(
+                text.trim_end(),
+                Style::default().fg(Color::Cyan),
+            )),
+            DiffLine::Context(text) => Line::from(format!(" {}", text)),
+            DiffLine::Added(text) => Line::from(Span::styled(
+                format!("+{}", text),
+                Style::default().fg(Color::Green),
+            )),
+            DiffLine::Removed(text) => Line::from(Span::styled(
+                format!("-{}", text),
+                Style::default().fg(Color::Red),
+            )),
+            DiffLine::Note(text) => Line::from(Span::styled(
+                text.as_str(),
+                Style::default().fg(Color::DarkGray).italic(),
+            )),
+        })
+        .collect();
+
+    let title = match app.selected_dotfile() {
+        Some(entry) => format!(" Diff: {} ", entry.config.name),
+        None => " Diff ".to_string(),
+    };
+
+    let content = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(" ↑/↓: scroll | PgUp/PgDn: page | i: install | Esc: back "),
+        )
+        .scroll((app.diff_scroll, 0));
 
-    render_footer(frame, chunks[1], "Preview");
+    frame.render_widget(content, chunks[0]);
+    render_footer(frame, chunks[1], app, "Diff");
+}
+
+fn render_problems(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Main content
+            Constraint::Length(3), // Footer
+        ])
+        .split(area);
+
+    let lines: Vec<Line> = if app.problems.is_empty() {
+        vec![Line::from(Span::styled(
+            "✓ All manifests are valid",
+            Style::default().fg(Color::Green),
+        ))]
+    } else {
+        app.problems
+            .iter()
+            .map(|problem| {
+                let location = match (problem.line, problem.column) {
+                    (Some(line), Some(column)) => {
+                        format!("{}:{}:{}", problem.file.display(), line, column)
+                    }
+                    _ => problem.file.display().to_string(),
+                };
+                Line::from(vec![
+                    Span::styled("✗ ", Style::default().fg(Color::Red)),
+                    Span::styled(location, Style::default().fg(Color::Cyan)),
+                    Span::raw(": "),
+                    Span::raw(problem.message.as_str()),
+                ])
+            })
+            .collect()
+    };
+
+    let content = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Problems ({}) ", app.problems.len()))
+                .title_bottom(" ↑/↓: scroll | Esc: back "),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((app.problems_scroll, 0));
+
+    frame.render_widget(content, chunks[0]);
+    render_footer(frame, chunks[1], app, "Problems");
+}
+
+fn render_profiles(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Main content
+            Constraint::Length(3), // Footer
+        ])
+        .split(area);
+
+    if app.profiles.is_empty() {
+        let msg = Paragraph::new(
+            "No profiles defined.\n\nAdd [profiles.<name>] tables to the settings file or a dwell-profile.toml next to your dotfiles.",
+        )
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Profiles ")
+                .title_bottom(" Esc: back "),
+        );
+        frame.render_widget(msg, chunks[0]);
+        render_footer(frame, chunks[1], app, "Profiles");
+        return;
+    }
+
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(chunks[0]);
+
+    let items: Vec<ListItem> = app
+        .profiles
+        .iter()
+        .enumerate()
+        .map(|(row, profile)| {
+            let style = if row == app.profile_cursor {
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let mut spans = vec![
+                Span::raw("  "),
+                Span::styled(
+                    profile.name.clone(),
+                    Style::default().fg(Color::Cyan).bold(),
+                ),
+                Span::styled(
+                    format!(" ({}) ", profile.config.entries.len()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ];
+            if app.is_profile_applied(profile) {
+                spans.push(Span::styled("● active", Style::default().fg(Color::Green)));
+            }
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Profiles ({}) ", app.profiles.len()))
+            .title_bottom(
+                " ↑/↓: navigate | Enter/a: apply | A: force apply | C: keep going | D: dry run | Esc: back ",
+            ),
+    );
+    let mut state = ListState::default().with_selected(Some(app.profile_cursor));
+    frame.render_stateful_widget(list, main_chunks[0], &mut state);
+
+    let Some(profile) = app.selected_profile() else {
+        render_footer(frame, chunks[1], app, "Profiles");
+        return;
+    };
+
+    let mut lines = vec![];
+    if !profile.config.description.is_empty() {
+        lines.push(Line::from(profile.config.description.as_str()));
+    }
+    lines.push(Line::from(Span::styled(
+        profile.source.display().to_string(),
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Line::from(""));
+    for item in &app.profile_items {
+        let (icon, color, note) = match item.action {
+            ProfileAction::UpToDate => ("✓", Color::Green, " up to date"),
+            ProfileAction::Install => ("↓", Color::Yellow, " will be installed"),
+            ProfileAction::Unknown => ("✗", Color::Red, " no such entry"),
+        };
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(icon, Style::default().fg(color).bold()),
+            Span::raw(" "),
+            Span::raw(item.name.as_str()),
+            Span::styled(note, Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+    if app.profile_items.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No entries",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    let pending = app
+        .profile_items
+        .iter()
+        .filter(|item| item.action == ProfileAction::Install)
+        .count();
+    let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} · {} to install ", profile.name, pending)),
+    );
+    frame.render_widget(details, main_chunks[1]);
+
+    render_footer(frame, chunks[1], app, "Profiles");
 }
 
 fn render_installing(frame: &mut Frame, app: &App) {
@@ -231,57 +978,223 @@ fn render_installing(frame: &mut Frame, app: &App) {
         ])
         .split(area);
 
-    let status_icon = if app.install_success {
-        Span::styled("✓", Style::default().fg(Color::Green).bold())
+    if !app.install_plans.is_empty() {
+        render_plans(frame, chunks[0], &app.install_plans);
+        render_footer(frame, chunks[1], app, "Installing");
+        return;
+    }
+
+    let (status_icon, status_text, status_color) = if app.is_installing() {
+        let spinner = SPINNER[(app.install_elapsed.as_millis() / 100) as usize % SPINNER.len()];
+        (
+            spinner,
+            format!("Installing... {}s", app.install_elapsed.as_secs()),
+            Color::Cyan,
+        )
+    } else if app.install_success {
+        (
+            "✓",
+            "Installation completed successfully!".to_string(),
+            Color::Green,
+        )
     } else {
-        Span::styled("✗", Style::default().fg(Color::Red).bold())
+        ("✗", "Installation failed!".to_string(), Color::Red)
     };
 
-    let status_text = if app.install_success {
-        "Installation completed successfully!"
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Installation Result ")
+        .title_bottom(if app.is_installing() {
+            " c: cancel "
+        } else {
+            " Press Enter or Esc to go back "
+        });
+    let inner = block.inner(chunks[0]);
+    frame.render_widget(block, chunks[0]);
+
+    let summary_height = if app.batch.is_empty() {
+        0
     } else {
-        "Installation failed!"
+        app.batch.len() as u16 + 2
     };
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(summary_height), // Per-entry results of a batch
+            Constraint::Length(5),              // Status
+            Constraint::Min(0),                 // Log
+        ])
+        .split(inner);
+    render_batch_summary(frame, sections[0], app);
 
-    let mut lines = vec![
+    let header = vec![
         Line::from(""),
         Line::from(vec![
             Span::raw("  "),
-            status_icon,
+            Span::styled(status_icon, Style::default().fg(status_color).bold()),
             Span::raw("  "),
-            Span::styled(
-                status_text,
-                Style::default().fg(if app.install_success {
-                    Color::Green
-                } else {
-                    Color::Red
-                }),
-            ),
+            Span::styled(status_text, Style::default().fg(status_color)),
         ]),
         Line::from(""),
-        Line::from(
-            Span::styled("Output:", Style::default().fg(Color::Yellow).bold()),
-        ),
+        Line::from(Span::styled(
+            "Output:",
+            Style::default().fg(Color::Yellow).bold(),
+        )),
         Line::from(""),
     ];
+    frame.render_widget(Paragraph::new(header), sections[1]);
 
-    if let Some(output) = &app.install_output {
-        for line in output.lines() {
-            lines.push(Line::from(format!("  {}", line)));
-        }
+    let lines: Vec<Line> = app
+        .install_output
+        .iter()
+        .map(|line| Line::from(format!("  {}", line)))
+        .collect();
+
+    // Keep the newest output in view
+    let scroll = lines.len().saturating_sub(sections[2].height as usize) as u16;
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), sections[2]);
+
+    render_footer(frame, chunks[1], app, "Installing");
+}
+
+fn render_batch_summary(frame: &mut Frame, area: Rect, app: &App) {
+    if app.batch.is_empty() {
+        return;
+    }
+
+    let lines: Vec<Line> = app
+        .batch
+        .iter()
+        .map(|item| {
+            let (icon, color, note) = match &item.outcome {
+                EntryOutcome::Pending => ("·", Color::DarkGray, String::new()),
+                EntryOutcome::Running => (
+                    SPINNER[(app.install_elapsed.as_millis() / 100) as usize % SPINNER.len()],
+                    Color::Cyan,
+                    String::new(),
+                ),
+                EntryOutcome::Installed => ("✓", Color::Green, String::new()),
+                EntryOutcome::Failed => ("✗", Color::Red, " failed".to_string()),
+                EntryOutcome::Skipped(reason) => {
+                    ("-", Color::Yellow, format!(" skipped: {}", reason))
+                }
+            };
+            Line::from(vec![
+                Span::raw("  "),
+                Span::styled(icon, Style::default().fg(color).bold()),
+                Span::raw(" "),
+                Span::raw(item.name.as_str()),
+                Span::styled(note, Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+
+    let done = app
+        .batch
+        .iter()
+        .filter(|item| !matches!(item.outcome, EntryOutcome::Pending | EntryOutcome::Running))
+        .count();
+    let summary = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::BOTTOM)
+            .title(format!(" Entries {}/{} ", done, app.batch.len())),
+    );
+    frame.render_widget(summary, area);
+}
+
+/// Lines describing one dry-run plan.
+fn plan_lines(plan: &InstallPlan) -> Vec<Line<'static>> {
+    let heading = |text: &'static str| {
+        Line::from(Span::styled(
+            text,
+            Style::default().fg(Color::Yellow).bold(),
+        ))
+    };
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("  "),
+            Span::styled("Dry run: ", Style::default().fg(Color::Cyan).bold()),
+            Span::raw(format!("nothing was changed installing {}", plan.entry)),
+        ]),
+        Line::from(""),
+        heading("Dependencies:"),
+    ];
+    if plan.dependencies.is_empty() {
+        lines.push(Line::from("  (none)"));
+    }
+    for dep in &plan.dependencies {
+        lines.push(dependency_line(dep));
     }
 
+    lines.push(Line::from(""));
+    lines.push(heading("Backups:"));
+    if plan.backups.is_empty() {
+        lines.push(Line::from("  (none)"));
+    }
+    for path in &plan.backups {
+        lines.push(Line::from(format!("  • {}", path.display())));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(heading("Files:"));
+    if plan.steps.is_empty() {
+        lines.push(Line::from("  (none)"));
+    }
+    for step in &plan.steps {
+        let (action, color) = match step.action {
+            DeployAction::Deploy(strategy) => (get_deploy_name(strategy), Color::Green),
+            DeployAction::Unchanged => ("unchanged", Color::DarkGray),
+            DeployAction::InPlace => ("in place", Color::DarkGray),
+            DeployAction::SourceMissing => ("missing", Color::Red),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<10}", action), Style::default().fg(color)),
+            Span::raw(format!(
+                "{} -> {}",
+                step.target.file,
+                step.target.dest.display()
+            )),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(heading("Command:"));
+    match &plan.command {
+        Some(command) => lines.push(Line::from(vec![
+            Span::styled(
+                format!("  $ {}", command.display()),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::styled(
+                format!("  (in {})", command.cwd.display()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ])),
+        None => lines.push(Line::from("  (none)")),
+    }
+
+    lines
+}
+
+fn render_plans(frame: &mut Frame, area: Rect, plans: &[InstallPlan]) {
+    let lines: Vec<Line> = plans.iter().flat_map(plan_lines).collect();
+    let title = match plans.len() {
+        1 => " Install Plan ".to_string(),
+        n => format!(" Install Plans ({}, in install order) ", n),
+    };
+
     let content = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" Installation Result ")
+                .title(title)
                 .title_bottom(" Press Enter or Esc to go back "),
         )
         .wrap(Wrap { trim: true });
 
-    frame.render_widget(content, chunks[0]);
-    render_footer(frame, chunks[1], "Installing");
+    frame.render_widget(content, area);
 }
 
 fn render_logo(frame: &mut Frame, area: Rect) {
@@ -327,15 +1240,34 @@ fn render_logo(frame: &mut Frame, area: Rect) {
     frame.render_widget(logo_widget, area);
 }
 
-fn render_footer(frame: &mut Frame, area: Rect, view_name: &str) {
-    let footer = Paragraph::new(Line::from(vec![
+fn render_footer(frame: &mut Frame, area: Rect, app: &App, view_name: &str) {
+    let mut spans = vec![
         Span::styled(" View: ", Style::default().fg(Color::DarkGray)),
         Span::styled(view_name, Style::default().fg(Color::Cyan).bold()),
         Span::styled(" | ", Style::default().fg(Color::DarkGray)),
         Span::styled("q", Style::default().fg(Color::Yellow)),
         Span::styled(": quit ", Style::default().fg(Color::DarkGray)),
-    ]))
-    .block(Block::default().borders(Borders::ALL));
+    ];
+    if app.dry_run {
+        spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(
+            "DRY RUN",
+            Style::default().fg(Color::Magenta).bold(),
+        ));
+    }
+    if app.keep_going {
+        spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(
+            "KEEP GOING",
+            Style::default().fg(Color::Magenta).bold(),
+        ));
+    }
+    if let Some(notice) = app.notice() {
+        spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(notice, Style::default().fg(Color::Green)));
+    }
+
+    let footer = Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL));
 
     frame.render_widget(footer, area);
 }
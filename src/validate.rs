@@ -0,0 +1,94 @@
+use crate::config::{line_column, DotfileEntry, ManifestProblem};
+use crate::palette::parse_value;
+use crate::scanner::ScanReport;
+use std::path::PathBuf;
+
+/// The manifest an entry was loaded from; TOML wins when both exist, like the scanner.
+pub fn manifest_path(entry: &DotfileEntry) -> PathBuf {
+    let toml_path = entry.path.join("dotwell.toml");
+    if toml_path.exists() {
+        toml_path
+    } else {
+        entry.path.join("dotwell.json")
+    }
+}
+
+/// Parse failures from the scan followed by semantic problems of every loaded entry.
+pub fn collect_problems(report: &ScanReport) -> Vec<ManifestProblem> {
+    let mut problems = report.problems.clone();
+    for entry in &report.entries {
+        problems.extend(check_entry(entry));
+    }
+    problems
+}
+
+/// Checks that parse fine but make no sense: missing files, targets for unlisted files,
+/// palette colors that are not colors.
+pub fn check_entry(entry: &DotfileEntry) -> Vec<ManifestProblem> {
+    let manifest = manifest_path(entry);
+    let content = std::fs::read_to_string(&manifest).unwrap_or_default();
+    let problem = |value: &str, message: String| {
+        let problem = ManifestProblem::new(&manifest, message);
+        match locate(&content, value) {
+            Some(offset) => {
+                let (line, column) = line_column(&content, offset);
+                problem.at(line, column)
+            }
+            None => problem,
+        }
+    };
+
+    let mut problems = vec![];
+    for file in &entry.config.files {
+        if !entry.path.join(file).exists() {
+            problems.push(problem(
+                file,
+                format!("listed file '{}' does not exist", file),
+            ));
+        }
+    }
+    for file in entry.config.targets.keys() {
+        if !entry.config.files.contains(file) {
+            problems.push(problem(
+                file,
+                format!("target '{}' is not listed in files", file),
+            ));
+        }
+    }
+    if let Some(screenshot) = &entry.config.screenshot {
+        if !entry.path.join(screenshot).is_file() {
+            problems.push(problem(
+                screenshot,
+                format!("screenshot '{}' does not exist", screenshot),
+            ));
+        }
+    }
+    for (name, value) in &entry.config.palette {
+        if parse_value(value).is_none() {
+            problems.push(problem(
+                name,
+                format!("palette color '{}' is not a hex or rgb() color", name),
+            ));
+        }
+    }
+    problems
+}
+
+/// Offset of the first quoted occurrence of `value`, or of a bare TOML key named `value`.
+fn locate(content: &str, value: &str) -> Option<usize> {
+    if let Some(offset) = content.find(&format!("\"{}\"", value)) {
+        return Some(offset);
+    }
+
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix(value) {
+            if rest.trim_start().starts_with('=') {
+                return Some(offset + line.len() - trimmed.len());
+            }
+        }
+        offset += line.len();
+    }
+    None
+}
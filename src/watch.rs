@@ -0,0 +1,98 @@
+use crate::profile::PROFILE_FILE;
+use crate::scanner::is_skipped_dir;
+use color_eyre::Result;
+use notify::event::{CreateKind, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Files whose changes can add, remove or alter entries or profiles.
+const WATCHED_FILES: [&str; 6] = [
+    "dotwell.toml",
+    "dotwell.json",
+    PROFILE_FILE,
+    ".gitignore",
+    ".ignore",
+    ".dwellignore",
+];
+
+/// Filesystem watch (inotify on Linux) over the directories the last scan
+/// walked, each watched on its own so skipped trees cost nothing.
+pub struct RootWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    watched: HashSet<PathBuf>,
+}
+
+impl RootWatcher {
+    pub fn new(dirs: &[PathBuf]) -> Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = Self {
+            watcher: notify::recommended_watcher(tx)?,
+            events,
+            watched: HashSet::new(),
+        };
+        watcher.watch(dirs);
+        Ok(watcher)
+    }
+
+    /// Moves the watch to `dirs`, e.g. after a rescan walked new directories.
+    /// Directories that disappeared in the meantime are skipped.
+    pub fn watch(&mut self, dirs: &[PathBuf]) {
+        let wanted: HashSet<PathBuf> = dirs.iter().cloned().collect();
+        for dir in self.watched.difference(&wanted) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        self.watched.retain(|dir| wanted.contains(dir));
+        // Subdirectories get their own watch once the scan walks them
+        let mode = RecursiveMode::NonRecursive;
+        for dir in wanted {
+            if self.watched.contains(&dir) {
+                continue;
+            }
+            if self.watcher.watch(&dir, mode).is_ok() {
+                self.watched.insert(dir);
+            }
+        }
+    }
+
+    /// Drains pending events and reports whether any of them could change
+    /// the scan result. Edits to ordinary config files are ignored.
+    pub fn has_changes(&self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            changed |= event.is_ok_and(|event| self.is_relevant(&event));
+        }
+        changed
+    }
+
+    fn is_relevant(&self, event: &Event) -> bool {
+        match event.kind {
+            EventKind::Access(_) => false,
+            // A walked directory went away or was renamed
+            _ if event.paths.iter().any(|path| self.watched.contains(path)) => true,
+            // A directory the scanner would walk appeared
+            EventKind::Create(CreateKind::Folder) | EventKind::Modify(ModifyKind::Name(_))
+                if event.paths.iter().any(|path| is_new_subdir(path)) =>
+            {
+                true
+            }
+            _ => event.paths.iter().any(|path| is_watched_file(path)),
+        }
+    }
+}
+
+fn is_new_subdir(path: &Path) -> bool {
+    path.is_dir()
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| !is_skipped_dir(name))
+}
+
+fn is_watched_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| WATCHED_FILES.contains(&name))
+}